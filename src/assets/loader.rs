@@ -78,6 +78,10 @@ impl<'w, 's> AssetsHandler<'w, 's> {
         self.add_asset(animation, path);
     }
 
+    pub fn add_game_config(&mut self, config: &mut Handle<crate::ingame::config::GameConfig>, path: &str) {
+        self.add_asset(config, path);
+    }
+
     pub fn add_standard_mesh(&mut self, handle: &mut Handle<Mesh>, mesh: Mesh) {
         *handle = self.meshes.add(mesh);
     }
@@ -43,6 +43,8 @@ pub mod command_ext {
                 AppState::TitleScreen => menu::title_screen::loader::TitleScreenLoader.apply(world),
                 AppState::Controls => menu::controls::loader::ControlsLoader.apply(world),
                 AppState::Instructions => menu::instructions::loader::InstructionsLoader.apply(world),
+                AppState::TrackSelect => menu::track_select::loader::TrackSelectLoader.apply(world),
+                AppState::Customize => menu::customize::loader::CustomizeLoader.apply(world),
                 _ => (),
             }
         }
@@ -51,8 +51,12 @@ pub struct GameAssets {
     pub smoke: Handle<Mesh>,
     pub hit_particle: Handle<Mesh>,
     pub bullet_mesh: Handle<Mesh>,
+    pub mine_mesh: Handle<Mesh>,
+    pub slow_field_mesh: Handle<Mesh>,
+    pub barrier_mesh: Handle<Mesh>,
 
     pub drive_animation: Handle<AnimationClip>,
+    pub game_config: Handle<crate::ingame::config::GameConfig>,
 
     pub controls_gamepad: GameTexture,
     pub controls_keyboard: GameTexture,
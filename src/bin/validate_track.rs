@@ -0,0 +1,260 @@
+// Checks that a track glb follows the node naming convention that the scene hook
+// in `ingame::setup` expects.
+//
+// cargo run --bin validate_track -- assets/models/track.glb
+use gltf::{mesh::Mode, Node};
+use std::collections::{BTreeMap, HashMap};
+use std::process::ExitCode;
+
+// one spawner per color in `GameState::kart_colors`
+const KART_COLORS: usize = 8;
+const WAYPOINT_KINDS: [&str; 4] = ["start", "quarter", "half", "finish"];
+// enough for a row of boxes across the track
+const MIN_ITEM_BOXES: usize = 3;
+
+struct Primitive {
+    name: String,
+    trimeshable: bool,
+}
+
+fn main() -> ExitCode {
+    let paths = std::env::args().skip(1).collect::<Vec<_>>();
+    if paths.is_empty() {
+        eprintln!("usage: validate_track <track.glb>...");
+        return ExitCode::FAILURE;
+    }
+
+    let mut failed = false;
+    for path in paths {
+        let problems = match gltf::import(&path) {
+            Ok((document, buffers, _)) => {
+                let mut primitives = vec![];
+                if let Some(scene) = document.scenes().next() {
+                    for node in scene.nodes() {
+                        collect_primitives(&node, &buffers, &mut primitives);
+                    }
+                }
+                validate(&primitives)
+            }
+            Err(error) => vec![format!("could not load: {}", error)],
+        };
+
+        if problems.is_empty() {
+            println!("{}: ok", path);
+        } else {
+            failed = true;
+            println!("{}: {} problem(s)", path, problems.len());
+            for problem in problems {
+                println!("  - {}", problem);
+            }
+        }
+    }
+
+    if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
+
+/// Names primitives the same way bevy's gltf loader names mesh entities
+fn collect_primitives(node: &Node, buffers: &[gltf::buffer::Data], primitives: &mut Vec<Primitive>) {
+    if let Some(mesh) = node.mesh() {
+        let mesh_name = mesh.name().unwrap_or("Mesh");
+        let count = mesh.primitives().len();
+        for primitive in mesh.primitives() {
+            let name = if count > 1 {
+                format!("{}.{}", mesh_name, primitive.index())
+            } else {
+                mesh_name.to_string()
+            };
+
+            let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data.0[..]));
+            let has_positions = reader.read_positions().map(|p| p.len() > 0).unwrap_or(false);
+            let index_count = reader.read_indices().map(|i| i.into_u32().count()).unwrap_or(0);
+            let trimeshable = has_positions
+                && primitive.mode() == Mode::Triangles
+                && index_count > 0
+                && index_count % 3 == 0;
+
+            primitives.push(Primitive { name, trimeshable });
+        }
+    }
+
+    for child in node.children() {
+        collect_primitives(&child, buffers, primitives);
+    }
+}
+
+/// Reads the trailing `.NNN` index of a path point. The unnumbered first copy
+/// (`Planea.path`) is point 0, the same as `PathAdder` reads it. Returns `None`
+/// for anything else that doesn't end in `.NNN`.
+fn trailing_index(name: &str) -> Option<usize> {
+    let (_, suffix) = name.rsplit_once('.')?;
+    if suffix.contains("path") {
+        return Some(0);
+    }
+
+    if suffix.is_empty() || !suffix.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    suffix.parse::<usize>().ok()
+}
+
+fn validate(primitives: &[Primitive]) -> Vec<String> {
+    let mut problems = vec![];
+    let mut waypoint_kinds = HashMap::new();
+    let mut path_indices = BTreeMap::<usize, Vec<&str>>::new();
+    let mut kart_spawners = 0;
+    let mut item_boxes = 0;
+
+    for primitive in primitives {
+        let name = primitive.name.as_str();
+        let needs_trimesh = name.contains("collide") || name.contains("waypoint") || name.contains("item_box");
+        if needs_trimesh && !primitive.trimeshable {
+            problems.push(format!("{} can't be turned into a trimesh collider (needs indexed triangles)", name));
+        }
+
+        if name.contains("waypoint") {
+            match WAYPOINT_KINDS.iter().find(|kind| name.contains(*kind)) {
+                Some(kind) => *waypoint_kinds.entry(*kind).or_insert(0) += 1,
+                None => problems.push(format!("{} is a waypoint but isn't named start, quarter, half or finish so it will be ignored", name)),
+            }
+        }
+
+        if name.contains("path") {
+            match trailing_index(name) {
+                Some(index) => path_indices.entry(index).or_default().push(name),
+                None if name.contains('.') => problems.push(format!("{} is a path point without a numeric .NNN index so it will be read as point 0", name)),
+                None => problems.push(format!("{} is a path point without a .NNN index so it will be ignored", name)),
+            }
+        }
+
+        if name.contains("kart_spawner") {
+            kart_spawners += 1;
+        }
+
+        if name.contains("item_box") {
+            item_boxes += 1;
+        }
+    }
+
+    for kind in WAYPOINT_KINDS {
+        if !waypoint_kinds.contains_key(kind) {
+            problems.push(format!("missing {} waypoint", kind));
+        }
+    }
+
+    if path_indices.is_empty() {
+        problems.push("no path points".to_string());
+    } else if path_indices.len() < 3 {
+        problems.push(format!("only {} path points, the racing line needs at least 3 to loop", path_indices.len()));
+    } else {
+        let last = *path_indices.keys().last().unwrap();
+        let missing = (0..=last).filter(|i| !path_indices.contains_key(i)).collect::<Vec<_>>();
+        if !missing.is_empty() {
+            problems.push(format!("path indices have gaps: {:?}", missing));
+        }
+    }
+    for (index, names) in path_indices.iter().filter(|(_, names)| names.len() > 1) {
+        problems.push(format!("path index {} is used by {}", index, names.join(", ")));
+    }
+
+    if kart_spawners < KART_COLORS {
+        problems.push(format!("only {} kart_spawner nodes, need {} (one per kart color)", kart_spawners, KART_COLORS));
+    }
+
+    if item_boxes == 0 {
+        problems.push("no item_box nodes, power-ups can't be picked up".to_string());
+    } else if item_boxes < MIN_ITEM_BOXES {
+        problems.push(format!("only {} item_box nodes, need at least {}", item_boxes, MIN_ITEM_BOXES));
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn primitive(name: &str) -> Primitive {
+        Primitive { name: name.to_string(), trimeshable: true }
+    }
+
+    fn valid_track() -> Vec<Primitive> {
+        let mut primitives = vec![primitive("collide_ground")];
+        primitives.extend(WAYPOINT_KINDS.iter().map(|kind| primitive(&format!("waypoint_{}", kind))));
+        primitives.extend((0..4).map(|i| primitive(&format!("path.{:03}", i))));
+        primitives.extend((0..KART_COLORS).map(|i| primitive(&format!("kart_spawner.{:03}", i))));
+        primitives.extend((0..MIN_ITEM_BOXES).map(|i| primitive(&format!("item_box.{:03}", i))));
+        primitives
+    }
+
+    #[test]
+    fn valid_track_has_no_problems() {
+        assert!(validate(&valid_track()).is_empty());
+    }
+
+    #[test]
+    fn reads_trailing_index() {
+        assert_eq!(trailing_index("path.012"), Some(12));
+        assert_eq!(trailing_index("Planea.path"), Some(0));
+        assert_eq!(trailing_index("path.abc"), None);
+        assert_eq!(trailing_index("path.12a"), None);
+        assert_eq!(trailing_index("path"), None);
+    }
+
+    #[test]
+    fn reports_non_numeric_path_indices() {
+        let mut primitives = valid_track();
+        primitives.push(primitive("path.abc"));
+        assert_eq!(validate(&primitives), vec!["path.abc is a path point without a numeric .NNN index so it will be read as point 0".to_string()]);
+    }
+
+    #[test]
+    fn colliders_need_a_trimesh() {
+        let mut primitives = valid_track();
+        primitives[0].trimeshable = false;
+        let problems = validate(&primitives);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("collide_ground"));
+    }
+
+    #[test]
+    fn reports_missing_waypoints_and_spawners() {
+        let primitives = valid_track()
+            .into_iter()
+            .filter(|p| p.name != "waypoint_half" && p.name != "kart_spawner.000")
+            .collect::<Vec<_>>();
+        let problems = validate(&primitives);
+        assert!(problems.contains(&"missing half waypoint".to_string()));
+        assert!(problems.iter().any(|p| p.starts_with("only 7 kart_spawner nodes")));
+    }
+
+    #[test]
+    fn reports_path_gaps_and_duplicates() {
+        let mut primitives = valid_track()
+            .into_iter()
+            .filter(|p| p.name != "path.002")
+            .collect::<Vec<_>>();
+        primitives.push(primitive("other_path.001"));
+        let problems = validate(&primitives);
+        assert!(problems.contains(&"path indices have gaps: [2]".to_string()));
+        assert!(problems.contains(&"path index 1 is used by path.001, other_path.001".to_string()));
+    }
+
+    #[test]
+    fn reports_missing_item_boxes() {
+        let mut primitives = valid_track()
+            .into_iter()
+            .filter(|p| p.name != "item_box.000")
+            .collect::<Vec<_>>();
+        primitives.last_mut().unwrap().trimeshable = false;
+        let problems = validate(&primitives);
+        assert!(problems.contains(&format!("only {} item_box nodes, need at least {}", MIN_ITEM_BOXES - 1, MIN_ITEM_BOXES)));
+        assert!(problems.iter().any(|p| p.starts_with("item_box.002 can't be turned into a trimesh")));
+
+        let primitives = valid_track()
+            .into_iter()
+            .filter(|p| !p.name.contains("item_box"))
+            .collect::<Vec<_>>();
+        assert_eq!(validate(&primitives), vec!["no item_box nodes, power-ups can't be picked up".to_string()]);
+    }
+}
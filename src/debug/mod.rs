@@ -3,7 +3,7 @@ use bevy::{
     prelude::*,
     app::AppExit,
 };
-use smooth_bevy_cameras::controllers::fps::{FpsCameraBundle, FpsCameraController, FpsCameraPlugin};
+use smooth_bevy_cameras::controllers::fps::{FpsCameraBundle, FpsCameraController};
 use smooth_bevy_cameras::{LookTransform, LookTransformBundle, Smoother};
 use crate::{AppState,};
 use crate::ingame::{tower::TowerSpawner, player::Player, bot::Bot};
@@ -13,7 +13,6 @@ impl Plugin for DebugPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup)
             .add_systems(Update, (fps_update, debug))
-            .add_plugins(FpsCameraPlugin::default())
             .add_plugins((FrameTimeDiagnosticsPlugin::default(),));
     }
 }
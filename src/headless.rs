@@ -0,0 +1,311 @@
+use bevy::{
+    app::{AppExit, ScheduleRunnerPlugin},
+    asset::AssetMetaCheck,
+    gltf::GltfPlugin,
+    log::LogPlugin,
+    prelude::*,
+    render::{mesh::MeshPlugin, primitives::Aabb},
+    scene::ScenePlugin,
+    text::FontLoader,
+    time::TimeUpdateStrategy,
+    utils::HashMap,
+    window::ExitCondition,
+};
+use bevy_camera_shake::CameraShakePlugin;
+use bevy_turborand::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+use std::time::Duration;
+use crate::assets::command_ext::*;
+use crate::{assets, ingame, ingame::{game_settings, kart, race}, shaders, ui, util, AppState, IngameState};
+
+const DEFAULT_RACES: usize = 1;
+const DEFAULT_SEED: u64 = 0;
+const FIXED_TIMESTEP: f64 = 1. / 60.;
+const RACE_TIME_LIMIT: f32 = 60. * 10.;
+
+/// Options for running bot-only races without a window, e.g.
+/// `go_tower_go --headless --races 500 --seed 42`
+pub struct SimulationOptions {
+    pub races: usize,
+    pub seed: u64,
+}
+
+impl SimulationOptions {
+    pub fn from_args() -> Option<Self> {
+        let args = std::env::args().collect::<Vec<_>>();
+        if !args.iter().any(|arg| arg == "--headless") {
+            return None;
+        }
+
+        let value_of = |flag: &str| args.iter().position(|arg| arg == flag).and_then(|i| args.get(i + 1));
+
+        Some(SimulationOptions {
+            races: value_of("--races").and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_RACES),
+            seed: value_of("--seed").and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_SEED),
+        })
+    }
+}
+
+/// Runs the races and prints the results
+pub fn run(options: SimulationOptions) {
+    let mut app = App::new();
+    app.insert_resource(AssetMetaCheck::Never)
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(FIXED_TIMESTEP)))
+        // one fixed update per frame instead of bevy's default 64Hz drifting against the frames
+        .insert_resource(Time::<Fixed>::from_seconds(FIXED_TIMESTEP))
+        .add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::ZERO)))
+        .add_plugins((
+            LogPlugin::default(),
+            // the game reads window and input events, there just aren't any
+            WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            },
+            bevy::input::InputPlugin,
+            TransformPlugin,
+            HierarchyPlugin,
+            AssetPlugin::default(),
+            ImagePlugin::default(),
+            MeshPlugin,
+            ScenePlugin,
+            GltfPlugin::default(),
+            AnimationPlugin,
+        ))
+        // materials, fonts and visibility are only ever drawn but the glTF scenes and the
+        // game still create them, so the types are registered without their render plugins
+        .init_asset::<StandardMaterial>()
+        .register_asset_reflect::<StandardMaterial>()
+        .init_asset::<shaders::ScrollingImageMaterial>()
+        .init_asset::<shaders::CustomMaterial>()
+        .init_asset::<shaders::BackgroundMaterial>()
+        .init_asset::<Font>()
+        .init_asset_loader::<FontLoader>()
+        .register_type::<Visibility>()
+        .register_type::<InheritedVisibility>()
+        .register_type::<ViewVisibility>()
+        .register_type::<Aabb>()
+        .add_plugins((
+            PhysicsPlugins::default(),
+            RngPlugin::new().with_rng_seed(options.seed),
+            CameraShakePlugin,
+        ))
+        .add_plugins((
+            assets::AssetsPlugin,
+            // util::UtilPlugin without kira's audio output
+            util::scene_hook::HookPlugin,
+            util::audio::SilentAudioPlugin,
+            util::input::InputPlugin,
+            util::settings::SettingsPlugin,
+            ingame::InGamePlugin,
+            ui::text_size::TextSizePlugin,
+            HeadlessPlugin {
+                races: options.races,
+                seed: options.seed,
+            },
+        ))
+        .add_state::<IngameState>()
+        .add_state::<AppState>();
+
+    app.run();
+}
+
+struct HeadlessPlugin {
+    races: usize,
+    seed: u64,
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SimulationStats {
+            races: self.races,
+            seed: self.seed,
+            ..default()
+        })
+        .add_systems(Startup, start_first_race)
+        .add_systems(OnEnter(IngameState::PreGame), skip_countdown)
+        .add_systems(
+            Update,
+            (track_racers, track_laps, track_towers, track_eliminations)
+                .chain()
+                .run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(Update, enforce_time_limit.run_if(in_state(IngameState::InGame)))
+        .add_systems(OnEnter(IngameState::EndGame), finish_race);
+    }
+}
+
+#[derive(Default)]
+struct RacerStats {
+    slot: usize,
+    color: Color,
+    lap_times: Vec<f32>,
+    towers: usize,
+    eliminated_at: Option<f32>,
+}
+
+#[derive(Default, Resource)]
+struct SimulationStats {
+    races: usize,
+    seed: u64,
+    current_race: usize,
+    racers: HashMap<Entity, RacerStats>,
+    wins_by_color: HashMap<String, usize>,
+    total_race_time: f32,
+    total_towers: usize,
+}
+
+fn hex(color: Color) -> String {
+    let [r, g, b, _] = color.as_rgba_u8();
+    format!("#{:02X}{:02X}{:02X}", r, g, b)
+}
+
+fn bot_only_game_state() -> game_settings::GameState {
+    game_settings::GameState {
+        number_of_players: 0,
+        enable_shadows: false,
+        enable_background: false,
+        ..default()
+    }
+}
+
+fn start_first_race(mut commands: Commands, mut game_state: ResMut<game_settings::GameState>) {
+    *game_state = bot_only_game_state();
+    commands.load_state(AppState::InGame);
+}
+
+fn skip_countdown(mut next_ingame_state: ResMut<NextState<IngameState>>) {
+    next_ingame_state.set(IngameState::InGame);
+}
+
+fn track_racers(
+    mut stats: ResMut<SimulationStats>,
+    karts: Query<(Entity, &kart::Kart), Added<kart::Kart>>,
+) {
+    for (entity, kart) in &karts {
+        let slot = stats.racers.len();
+        stats.racers.insert(entity, RacerStats {
+            slot,
+            color: kart.0,
+            ..default()
+        });
+    }
+}
+
+fn track_laps(
+    mut stats: ResMut<SimulationStats>,
+    game_state: Res<game_settings::GameState>,
+    mut lap_completed_event_reader: EventReader<race::events::LapCompleted>,
+) {
+    for lap_completed in lap_completed_event_reader.read() {
+        if let Some(racer) = stats.racers.get_mut(&lap_completed.entity) {
+            let lap_time = game_state.game_time - racer.lap_times.iter().sum::<f32>();
+            racer.lap_times.push(lap_time);
+        }
+    }
+}
+
+fn track_towers(
+    mut stats: ResMut<SimulationStats>,
+    mut tower_placed_event_reader: EventReader<race::events::TowerPlaced>,
+) {
+    for tower_placed in tower_placed_event_reader.read() {
+        if let Some(racer) = stats.racers.get_mut(&tower_placed.owner) {
+            racer.towers += 1;
+        }
+    }
+}
+
+fn track_eliminations(
+    mut stats: ResMut<SimulationStats>,
+    game_state: Res<game_settings::GameState>,
+    mut kart_eliminated_event_reader: EventReader<race::events::KartEliminated>,
+) {
+    for kart_eliminated in kart_eliminated_event_reader.read() {
+        if let Some(racer) = stats.racers.get_mut(&kart_eliminated.entity) {
+            racer.eliminated_at.get_or_insert(game_state.game_time);
+        }
+    }
+}
+
+fn enforce_time_limit(
+    game_state: Res<game_settings::GameState>,
+    mut next_ingame_state: ResMut<NextState<IngameState>>,
+) {
+    if game_state.game_time > RACE_TIME_LIMIT {
+        next_ingame_state.set(IngameState::EndGame);
+    }
+}
+
+fn finish_race(
+    mut commands: Commands,
+    mut stats: ResMut<SimulationStats>,
+    mut game_state: ResMut<game_settings::GameState>,
+    mut next_ingame_state: ResMut<NextState<IngameState>>,
+    mut exit: EventWriter<AppExit>,
+    karts: Query<(Entity, Option<&race::placement::Place>), With<kart::Kart>>,
+) {
+    stats.current_race += 1;
+    let race_time = game_state.game_time;
+    let winner = karts
+        .iter()
+        .min_by_key(|(_, place)| place.map(|p| p.0).unwrap_or(usize::MAX))
+        .map(|(entity, _)| entity);
+
+    match winner.and_then(|w| stats.racers.get(&w)) {
+        Some(racer) => println!(
+            "race {}/{} (seed {}): kart {} {} won after {:.1}s",
+            stats.current_race, stats.races, stats.seed, racer.slot, hex(racer.color), race_time
+        ),
+        None => println!(
+            "race {}/{} (seed {}): no winner after {:.1}s",
+            stats.current_race, stats.races, stats.seed, race_time
+        ),
+    }
+
+    let mut racers = stats.racers.values().collect::<Vec<_>>();
+    racers.sort_by_key(|racer| racer.slot);
+    for racer in racers.iter() {
+        let best_lap = racer.lap_times.iter().cloned().reduce(f32::min);
+        println!(
+            "  kart {} {}: laps [{}] best {}, {} towers, {}",
+            racer.slot,
+            hex(racer.color),
+            racer.lap_times.iter().map(|t| format!("{:.1}", t)).collect::<Vec<_>>().join(", "),
+            best_lap.map(|t| format!("{:.1}s", t)).unwrap_or("-".to_string()),
+            racer.towers,
+            racer.eliminated_at.map(|t| format!("eliminated at {:.1}s", t)).unwrap_or("finished".to_string()),
+        );
+    }
+
+    let towers_this_race = racers.iter().map(|racer| racer.towers).sum::<usize>();
+    let winning_color = winner.and_then(|w| stats.racers.get(&w)).map(|racer| hex(racer.color));
+    stats.total_towers += towers_this_race;
+    stats.total_race_time += race_time;
+    if let Some(color) = winning_color {
+        *stats.wins_by_color.entry(color).or_insert(0) += 1;
+    }
+    stats.racers.clear();
+
+    if stats.current_race < stats.races {
+        *game_state = bot_only_game_state();
+        next_ingame_state.set(IngameState::Disabled);
+        commands.load_state(AppState::InGame);
+    } else {
+        let races = stats.current_race as f32;
+        println!(
+            "{} races: average race {:.1}s, average towers per race {:.1}",
+            stats.current_race,
+            stats.total_race_time / races,
+            stats.total_towers as f32 / races
+        );
+
+        let mut wins = stats.wins_by_color.iter().collect::<Vec<_>>();
+        wins.sort_by(|a, b| b.1.cmp(a.1));
+        for (color, count) in wins {
+            println!("  {} won {} ({:.1}%)", color, count, (*count as f32 / races) * 100.);
+        }
+
+        exit.send(AppExit);
+    }
+}
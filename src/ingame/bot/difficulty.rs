@@ -0,0 +1,75 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+pub struct DifficultyProfile {
+    /// How far along the track the bot looks for corners
+    pub lookahead: f32,
+    /// Fastest the bot will take a right angle corner
+    pub corner_speed: f32,
+    /// How much the bot's random bias pulls its steering off line
+    pub wobble: f32,
+    /// Enemy bullets, mines and towers closer than this get steered around, 0 ignores them
+    pub awareness: f32,
+    /// Karts closer than this in front of the bot get passed, 0 never overtakes
+    pub overtake_distance: f32,
+    /// Seconds between tower placements
+    pub tower_delay: f32,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    fn index(&self) -> usize {
+        Difficulty::ALL.iter().position(|difficulty| difficulty == self).unwrap_or(0)
+    }
+
+    pub fn next(&self) -> Self {
+        Difficulty::ALL[(self.index() + 1) % Difficulty::ALL.len()]
+    }
+
+    pub fn previous(&self) -> Self {
+        Difficulty::ALL[(self.index() + Difficulty::ALL.len() - 1) % Difficulty::ALL.len()]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    pub fn profile(&self) -> DifficultyProfile {
+        match self {
+            Difficulty::Easy => DifficultyProfile {
+                lookahead: 20.,
+                corner_speed: 12.,
+                wobble: 0.6,
+                awareness: 0.,
+                overtake_distance: 0.,
+                tower_delay: 2.,
+            },
+            Difficulty::Normal => DifficultyProfile {
+                lookahead: 30.,
+                corner_speed: 16.,
+                wobble: 0.3,
+                awareness: 15.,
+                overtake_distance: 10.,
+                tower_delay: 1.,
+            },
+            Difficulty::Hard => DifficultyProfile {
+                lookahead: 40.,
+                corner_speed: 20.,
+                wobble: 0.1,
+                awareness: 25.,
+                overtake_distance: 15.,
+                tower_delay: 0.75,
+            },
+        }
+    }
+}
@@ -1,8 +1,20 @@
 use bevy::prelude::*;
 use crate::{AppState, IngameState};
+use super::{controller, path, tower, kart, bullet, config, game_settings};
 use bevy_turborand::prelude::*;
-use super::{controller, path, race, tower, kart, assets};
 use bevy_xpbd_3d::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+
+mod difficulty;
+mod planner;
+
+pub use self::difficulty::{Difficulty, DifficultyProfile};
+use self::planner::{place_towers, TowerPlacer};
+
+/// How far off the middle of the track a bot will drive, as a fraction of the track width
+const LANE_WIDTH: f32 = 0.6;
+/// How quickly a bot moves between lanes, in lanes per second
+const LANE_CHANGE_SPEED: f32 = 1.5;
 
 #[cfg(feature = "gizmos")]
 use bevy::gizmos::gizmos::Gizmos;
@@ -10,21 +22,27 @@ use bevy::gizmos::gizmos::Gizmos;
 pub struct BotPlugin;
 impl Plugin for BotPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (find_target, move_bots).chain()
+        app.add_systems(Update, move_bots
+                        .in_set(BotSet)
                         .run_if(in_state(AppState::InGame).and_then(in_state(IngameState::InGame)))
                         )
             .add_systems(
                 FixedUpdate,
-                ((place_towers, ).chain()).run_if(in_state(AppState::InGame)),
+                ((place_towers, ).chain()).in_set(BotSet).run_if(in_state(AppState::InGame)),
             );
     }
 }
 
+/// The systems that let bots drive and place towers
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BotSet;
+
 #[derive(Component, Default)]
 pub struct Bot {
-    target: Option<usize>,
     spawn_delay: Timer,
     random: f32,
+    /// Offset from the middle of the track, -1 is the left edge and 1 is the right
+    lane: f32,
 }
 
 impl Bot {
@@ -41,113 +59,78 @@ impl Bot {
 pub struct BotBundle {
     bot: Bot,
     tower_placer: TowerPlacer, 
+    /// Bot decisions draw from their own rng, bots don't run during replays and
+    /// the `GlobalRng` has to be drawn from the same way whether they do or not
+    rng: RngComponent,
 }
 
 impl BotBundle {
-    pub fn new(normalized_rand: f32, positive_rand: f32) -> Self {
+    pub fn new(normalized_rand: f32, positive_rand: f32, rng: RngComponent) -> Self {
         BotBundle {
             bot: Bot::new(normalized_rand),
-            tower_placer: TowerPlacer::new(positive_rand)
-        }
-    }
-}
-
-#[derive(Component, Default)]
-pub struct TowerPlacer {
-    min_percentage_into_track: f32,
-}
-
-impl TowerPlacer {
-    pub fn new(random: f32) -> Self {
-        TowerPlacer {
-            min_percentage_into_track: 0.1 + (random % 0.8),
-            ..default()
-        }
-    }
-}
-
-fn place_towers(
-    mut commands: Commands,
-    mut bots: Query<(Entity, &mut Bot, &mut TowerPlacer, &kart::Kart, &kart::KartColor)>,
-    game_assets: Res<assets::GameAssets>,
-    waypoints: Query<&race::WayPoint>,
-    mut global_rng: ResMut<GlobalRng>,
-    path_manager: Res<path::PathManager>,
-    time: Res<Time>,
-) {
-    for (entity, mut b, mut tower_placer, kart, kart_color) in &mut bots {
-        if !b.spawn_delay.tick(time.delta()).finished() {
-            continue;
-        }
-
-        if let Some(bot_index) = b.target {
-            let waypoints =
-            waypoints.iter()
-                     .fold((None, None), |mut acc, e| {
-                         if e.0 == race::WayPoints::Start && e.1.is_some() {
-                             acc.0 = e.1;
-                         }
-                         if e.0 == race::WayPoints::Finish && e.1.is_some() {
-                             acc.1 = e.1;
-                         }
-
-                         acc
-                     });
-            if let (Some(start_index), Some(mut finish_index)) = waypoints {
-
-                if finish_index == 0 {
-                    finish_index = path_manager.get_previous(finish_index).unwrap_or(20); // just picking something
-                }
-
-                if bot_index < start_index || bot_index > finish_index {
-                    continue;
-                }
-
-                if (bot_index - start_index) as f32 / finish_index as f32 > tower_placer.min_percentage_into_track {
-                    commands.add(tower::TowerSpawner {
-                        entity,
-                        material: game_assets.kart_colors[&kart_color.0].clone_weak(),
-                    });
-
-                    b.spawn_delay = Timer::from_seconds(1., TimerMode::Once);
-                    tower_placer.min_percentage_into_track = 0.1 + (global_rng.f32() % 0.8);
-                } 
-            }
-        }
-    }
-}
-
-fn find_target(
-    mut bots: Query<(&mut Bot, &Transform)>,
-    path_manager: Res<path::PathManager>,
-) {
-    for (mut bot, transform) in &mut bots {
-        match bot.target {
-            Some(target) => {
-                let target_point = path_manager.get(target);
-                let distance = transform.translation.distance(target_point);
-                if distance < 20.0 {
-                    bot.target = path_manager.get_next(target);
-                }
-            },
-            None => {
-                bot.target = path_manager.get_closest_index(transform.translation);
-            }
+            tower_placer: TowerPlacer::new(positive_rand),
+            rng,
         }
     }
 }
 
 fn move_bots(
-    bots: Query<(Entity, &Bot, &Transform, &LinearVelocity)>,
+    mut bots: Query<(Entity, &mut Bot, &Transform, &LinearVelocity)>,
+    karts: Query<(Entity, &Transform), With<kart::Kart>>,
+    bullets: Query<(&bullet::Bullet, &Transform)>,
+    mines: Query<(&tower::weapons::Mine, &Transform)>,
+    tower_bodies: Query<(&tower::TowerBody, &Transform)>,
     path_manager: Res<path::PathManager>,
+    game_state: Res<game_settings::GameState>,
+    game_config: Res<config::GameConfig>,
+    time: Res<Time>,
     mut movement_event_writer: EventWriter<controller::MovementEvent>,
 
     #[cfg(feature = "gizmos")]
     mut gizmos: Gizmos,
 ) {
-    for (entity, bot, transform, linear_velocity) in &bots {
-        if let Some(target) = bot.target {
-            let target = path_manager.get(target);
+    let profile = game_state.bot_difficulty.profile();
+    let track_width = game_config.track_width;
+
+    for (entity, mut bot, transform, linear_velocity) in &mut bots {
+        if !path_manager.is_empty() {
+            let position = transform.translation;
+            let progress = path_manager.progress_at(position);
+            let target = path_manager.point_at(progress + track_width * 2.);
+            let path_forward = (path_manager.tangent_at(progress + track_width * 2.) * Vec3::new(1., 0., 1.)).normalize_or_zero();
+            let path_right = path_forward.cross(Vec3::Y);
+            let lateral = |point: Vec3| (point - target).dot(path_right) / track_width;
+            let is_ahead = |point: Vec3, range: f32| {
+                let offset = point - position;
+                offset.dot(path_forward) > 0. && offset.length() < range
+            };
+            let current_lane = lateral(position);
+
+            // pull out to whichever side of the kart in front has more room
+            let mut desired_lane = karts.iter()
+                .filter(|(other, transform)| *other != entity && is_ahead(transform.translation, profile.overtake_distance))
+                .map(|(_, transform)| lateral(transform.translation))
+                .filter(|other_lane| (other_lane - current_lane).abs() < LANE_WIDTH * 0.5)
+                .min_by(|a, b| (a - current_lane).abs().total_cmp(&(b - current_lane).abs()))
+                .map(|other_lane| if other_lane > 0. { -LANE_WIDTH } else { LANE_WIDTH })
+                .unwrap_or(0.);
+
+            let dangers = bullets.iter()
+                .filter(|(bullet, _)| bullet.owner != entity)
+                .map(|(_, transform)| transform.translation)
+                .chain(mines.iter().filter(|(mine, _)| mine.owner != entity).map(|(_, transform)| transform.translation))
+                .chain(tower_bodies.iter().filter(|(body, _)| body.owner != entity).map(|(_, transform)| transform.translation))
+                .filter(|danger| is_ahead(*danger, profile.awareness));
+            for danger in dangers {
+                let danger_lane = lateral(danger);
+                let closeness = 1. - danger.distance(position) / profile.awareness;
+                desired_lane += if danger_lane > current_lane { -closeness } else { closeness };
+            }
+
+            let desired_lane = desired_lane.clamp(-LANE_WIDTH, LANE_WIDTH);
+            let lane_change = (desired_lane - bot.lane).clamp(-LANE_CHANGE_SPEED * time.delta_seconds(), LANE_CHANGE_SPEED * time.delta_seconds());
+            bot.lane += lane_change;
+            let target = target + path_right * bot.lane * track_width;
 
             #[cfg(feature = "gizmos")]
             {
@@ -163,22 +146,24 @@ fn move_bots(
             if dot < -0. {
                 movement_event_writer.send(controller::MovementEvent {
                     entity,
-                    action: controller::MovementAction::Turn(0.1 * dot.abs() + (bot.random * 0.6)),
+                    action: controller::MovementAction::Turn(0.1 * dot.abs() + (bot.random * profile.wobble)),
                 });
             } else if dot > 0. {
                 movement_event_writer.send(controller::MovementEvent {
                     entity,
-                    action: controller::MovementAction::Turn(-0.1 * dot.abs() + (bot.random * 0.6)),
+                    action: controller::MovementAction::Turn(-0.1 * dot.abs() + (bot.random * profile.wobble)),
                 });
             }
 
-//            if dot.abs() < 10.0 {
-                movement_event_writer.send(controller::MovementEvent {
-                    entity,
-                    action: controller::MovementAction::Gas,
-                });
-//            } 
+            // a right angle corner coming up means slowing down to corner_speed, sharper ones even more
+            let curvature = path_manager.turn_over(progress, profile.lookahead);
+            let corner_speed = profile.corner_speed * FRAC_PI_2 / curvature.max(0.01);
+            let speed = (linear_velocity.0 * Vec3::new(1., 0., 1.)).length();
 
+            movement_event_writer.send(controller::MovementEvent {
+                entity,
+                action: if speed > corner_speed { controller::MovementAction::Brake } else { controller::MovementAction::Gas },
+            });
         } else {
             movement_event_writer.send(controller::MovementEvent {
                 entity,
@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+use bevy_turborand::prelude::*;
+use crate::ingame::{config, controller, game_settings, kart, path, points, tower};
+use super::Bot;
+
+/// How many path points ahead of the bot are considered for the next tower
+const PLAN_AHEAD: usize = 12;
+/// Rivals behind the bot and within this many path points of a spot will drive past it soon
+const RIVAL_WINDOW: f32 = 15.;
+const RIVAL_SCORE: f32 = 1.0;
+/// Per radian the path turns through just after the spot
+const CORNER_SCORE: f32 = 1.0;
+/// Per tower already covering the spot
+const CROWDED_PENALTY: f32 = 1.5;
+
+#[derive(Clone, Copy)]
+struct TowerPlan {
+    /// Distance along the track
+    progress: f32,
+    kind: tower::TowerKind,
+}
+
+/// Picks where along the track a bot's next tower goes
+#[derive(Component, Default)]
+pub struct TowerPlacer {
+    /// Spots scoring lower than this aren't worth spending credits on
+    min_score: f32,
+    plan: Option<TowerPlan>,
+}
+
+impl TowerPlacer {
+    pub fn new(random: f32) -> Self {
+        TowerPlacer {
+            min_score: 1.0 + (random % 1.0),
+            ..default()
+        }
+    }
+}
+
+pub fn place_towers(
+    mut kart_action_event_writer: EventWriter<controller::KartActionEvent>,
+    mut bots: Query<(Entity, &mut Bot, &mut TowerPlacer, &mut RngComponent, &Transform, &points::Points, &kart::KartClass)>,
+    karts: Query<(Entity, &Transform), With<kart::Kart>>,
+    towers: Query<&Transform, With<tower::Tower>>,
+    path_manager: Res<path::PathManager>,
+    game_state: Res<game_settings::GameState>,
+    game_config: Res<config::GameConfig>,
+    time: Res<Time>,
+) {
+    if path_manager.is_empty() {
+        return;
+    }
+
+    let profile = game_state.bot_difficulty.profile();
+    let spacing = path_manager.point_spacing();
+    let kart_progress = karts.iter()
+                             .map(|(entity, transform)| (entity, path_manager.progress_at(transform.translation)))
+                             .collect::<Vec<_>>();
+    let tower_positions = towers.iter().map(|transform| transform.translation).collect::<Vec<_>>();
+    let crowded_distance = game_config.track_width * 3.;
+
+    for (entity, mut bot, mut tower_placer, mut rng, transform, points, class) in &mut bots {
+        if !bot.spawn_delay.tick(time.delta()).finished() {
+            continue;
+        }
+
+        let current = path_manager.progress_at(transform.translation);
+
+        let score = |progress: f32| {
+            // karts between the bot and the spot would already be past it by the time the tower is up
+            let ahead_of_bot = path_manager.distance_between(current, progress);
+            let rivals = kart_progress.iter()
+                                      .filter(|(kart, kart_progress)| {
+                                          let behind = path_manager.distance_between(*kart_progress, progress);
+                                          *kart != entity && behind > ahead_of_bot && behind <= RIVAL_WINDOW * spacing
+                                      })
+                                      .count();
+            // nobody but the bot would drive past a tower here any time soon
+            if rivals == 0 {
+                return 0.;
+            }
+
+            let point = path_manager.point_at(progress);
+            let crowding = tower_positions.iter().filter(|tower| tower.distance(point) < crowded_distance).count();
+
+            rivals as f32 * RIVAL_SCORE
+                + path_manager.turn_over(progress, spacing * 3.) * CORNER_SCORE
+                - crowding as f32 * CROWDED_PENALTY
+        };
+
+        if let Some(plan) = tower_placer.plan {
+            let has_reached = path_manager.distance_between(plan.progress, current) < spacing;
+            let has_driven_past = path_manager.distance_between(current, plan.progress) > spacing * PLAN_AHEAD as f32;
+            if has_reached {
+                tower_placer.plan = None;
+                kart_action_event_writer.send(controller::KartActionEvent { entity, action: controller::KartAction::PlaceTower(plan.kind) });
+                bot.spawn_delay = Timer::from_seconds(profile.tower_delay, TimerMode::Once);
+            } else if has_driven_past {
+                tower_placer.plan = None;
+            }
+            continue;
+        }
+
+        let best = (0..PLAN_AHEAD)
+            .map(|step| current + step as f32 * spacing)
+            .map(|progress| (progress, score(progress)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        if let Some((progress, best_score)) = best {
+            let kind = tower::TowerKind::ALL[rng.usize(0..tower::TowerKind::ALL.len())];
+            let cost = class.stats(&game_config).tower_cost(kind.cost(&game_config));
+            // a bot with plenty of credits is less picky about where it spends them
+            let min_score = if points.0 >= cost * 2 { tower_placer.min_score * 0.5 } else { tower_placer.min_score };
+            // otherwise it keeps saving until it can afford a good spot
+            if best_score >= min_score && points.0 >= cost {
+                tower_placer.plan = Some(TowerPlan { progress, kind });
+            }
+        }
+    }
+}
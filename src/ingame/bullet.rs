@@ -87,16 +87,17 @@ pub fn animate_hit(
     mut commands: Commands,
     mut hits: Query<(&BulletHit, &mut Transform, &Handle<StandardMaterial>, &Parent)>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    game_config: Res<config::GameConfig>,
     time: Res<Time>,
 ) {
     for (hit, mut transform, material, parent) in hits.iter_mut() {
 //      transform.rotate(Quat::from_rotation_x(time.delta_seconds()));
 //      transform.rotate(Quat::from_rotation_y(time.delta_seconds()));
-        transform.scale *= 1.0 - (time.delta_seconds() * config::HIT_SHRINK_SPEED);
+        transform.scale *= 1.0 - (time.delta_seconds() * game_config.hit_shrink_speed);
 
         let target = transform
             .translation
-            .lerp(hit.move_toward, time.delta_seconds() * config::HIT_SPEED);
+            .lerp(hit.move_toward, time.delta_seconds() * game_config.hit_speed);
         if !target.is_nan() {
             transform.translation = target;
         }
@@ -124,8 +125,10 @@ pub fn handle_create_hit_event(
     audio: Res<Audio>,
     game_assets: Res<assets::GameAssets>,
     game_state: Res<game_settings::GameState>,
-    mut global_rng: ResMut<GlobalRng>,
 ) {
+    // particles only spawn for hits on screen so they don't draw from the GlobalRng,
+    // otherwise the race would play out differently depending on what the camera sees
+    let rng = Rng::new();
     for event in create_hit_event_reader.read() {
         let position = event.position;
 
@@ -138,13 +141,13 @@ pub fn handle_create_hit_event(
                 let sound = audio.play(game_assets.sfx_hit.clone()).with_volume(0.).handle();
                 emitter.instances.push(sound);
             }
-            let inner_mesh_x = (global_rng.f32_normalized() * 25.) / 100.0;
-            let inner_mesh_z = (global_rng.f32_normalized() * 25.) / 100.0;
+            let inner_mesh_x = (rng.f32_normalized() * 25.) / 100.0;
+            let inner_mesh_z = (rng.f32_normalized() * 25.) / 100.0;
 
 
-            let move_toward_x = global_rng.f32_normalized();
-            let move_toward_y = global_rng.f32();
-            let move_toward_z = global_rng.f32_normalized();
+            let move_toward_x = rng.f32_normalized();
+            let move_toward_y = rng.f32();
+            let move_toward_z = rng.f32_normalized();
             let move_toward = Vec3::new(move_toward_x, move_toward_y, move_toward_z);
 
             let mut particle = 
@@ -197,6 +200,7 @@ pub struct BulletSpawner<C: Component + Clone>  {
     pub color: Color,
     pub kart_color: kart::KartColor,
     pub speed: f32,
+    pub size: f32,
     pub cleanup_marker: C
 }
 impl<C: Component + Clone>  Command for BulletSpawner<C> {
@@ -218,6 +222,7 @@ impl<C: Component + Clone>  Command for BulletSpawner<C> {
                 material,
                 transform: Transform::from_translation(self.spawn_point)
                     .with_rotation(Quat::from_axis_angle(Vec3::Y, TAU * 0.75))
+                    .with_scale(Vec3::splat(self.size))
                     .looking_to(self.direction, Vec3::Y),
                 ..default()
             },
@@ -1,9 +1,10 @@
-use bevy::{prelude::*, ecs::system::Command};
+use bevy::{prelude::*, ecs::system::Command, window::{PrimaryWindow, WindowResized}, render::{camera::Viewport, view::RenderLayers}, core_pipeline::clear_color::ClearColorConfig};
 use smooth_bevy_cameras::{
-    controllers::fps::FpsCameraController,
+    controllers::fps::{FpsCameraController, FpsCameraPlugin},
     LookTransformPlugin, 
 };
-use super::{player, controller};
+use super::{player, controller, game_settings};
+use crate::AppState;
 use bevy::transform::TransformSystem;
 use bevy_xpbd_3d::PhysicsSet;
 use bevy_turborand::prelude::*;
@@ -13,25 +14,81 @@ use bevy_camera_shake::{RandomSource, Shake3d};
 pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(LookTransformPlugin)
+        app.add_plugins((LookTransformPlugin, FpsCameraPlugin::default()))
 //            .add_systems(Update, follow_player.after(controller::apply_movement_damping));
             .add_systems(
                 PostUpdate,
                follow_player 
                     .after(PhysicsSet::Sync)
                     .before(TransformSystem::TransformPropagate),
-            );
+            )
+            .add_systems(Update, set_camera_viewports.run_if(in_state(AppState::InGame)));
+    }
+}
+
+/// A camera following the player with the matching index
+#[derive(Component)]
+pub struct PlayerCamera(pub usize);
+
+/// The render layer only the player's camera draws, things that turn to face the
+/// camera spawn a copy per player on these so each viewport sees its own copy facing it
+pub fn player_layer(player: usize) -> RenderLayers {
+    RenderLayers::layer(player as u8 + 1)
+}
+
+/// The section of the screen, in 0..1 units, given to a player's view
+pub fn viewport_rect(player: usize, number_of_players: usize) -> Rect {
+    match (number_of_players, player) {
+        (2, 0) => Rect::new(0.0, 0.0, 1.0, 0.5),
+        (2, _) => Rect::new(0.0, 0.5, 1.0, 1.0),
+        (3, 0) => Rect::new(0.0, 0.0, 1.0, 0.5),
+        (3, 1) => Rect::new(0.0, 0.5, 0.5, 1.0),
+        (3, _) => Rect::new(0.5, 0.5, 1.0, 1.0),
+        (4, 0) => Rect::new(0.0, 0.0, 0.5, 0.5),
+        (4, 1) => Rect::new(0.5, 0.0, 1.0, 0.5),
+        (4, 2) => Rect::new(0.0, 0.5, 0.5, 1.0),
+        (4, _) => Rect::new(0.5, 0.5, 1.0, 1.0),
+        _ => Rect::new(0.0, 0.0, 1.0, 1.0),
+    }
+}
+
+fn set_camera_viewports(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut resize_events: EventReader<WindowResized>,
+    mut cameras: Query<(&mut Camera, &PlayerCamera)>,
+    added_cameras: Query<(), Added<PlayerCamera>>,
+    game_state: Res<game_settings::GameState>,
+) {
+    let was_resized = resize_events.read().count() > 0;
+    if game_state.number_of_players <= 1 || (!was_resized && added_cameras.is_empty()) {
+        return;
+    }
+
+    if let Ok(window) = windows.get_single() {
+        let window_size = Vec2::new(window.physical_width() as f32, window.physical_height() as f32);
+        for (mut camera, player_camera) in &mut cameras {
+            let rect = viewport_rect(player_camera.0, game_state.number_of_players);
+            camera.viewport = Some(Viewport {
+                physical_position: (rect.min * window_size).as_uvec2(),
+                physical_size: (rect.size() * window_size).as_uvec2(),
+                ..default()
+            });
+        }
     }
 }
 
 fn follow_player(
-    players: Query<(&Transform, &LinearVelocity, ), With<player::Player>>,
-    mut cameras: Query<&mut Transform, (With<Camera>, Without<FpsCameraController>, Without<player::Player>)>,
+    players: Query<(&Transform, &LinearVelocity, &player::Player)>,
+    mut cameras: Query<(&mut Transform, &PlayerCamera), (Without<FpsCameraController>, Without<player::Player>)>,
     time: Res<Time>,
 //  mut cameras: Query<&mut Transform, >,
 ) {
-    for mut c_transform in &mut cameras {
-        for (player_transform, linear_velocity) in &players {
+    for (mut c_transform, player_camera) in &mut cameras {
+        for (player_transform, linear_velocity, player) in &players {
+            if player.0 != player_camera.0 {
+                continue;
+            }
+
             let back_offset = 15.0;
             let height = 5.0;
             let new_translation = player_transform.translation + (player_transform.back() * back_offset) + Vec3::new(0., height, 0.);
@@ -49,49 +106,84 @@ fn follow_player(
 }
 
 pub struct SpawnCamera<C: Component + Clone> {
-    pub cleanup_marker: C
+    pub cleanup_marker: C,
+    pub number_of_players: usize,
 }
 impl<C: Component + Clone> Command for SpawnCamera<C> {
     fn apply(self, world: &mut World) {
+        let split_screen = self.number_of_players > 1;
 
-        let shake_id = world 
-            .spawn((Shake3d {
-                max_offset: Vec3::new(0.0, 0.0, 0.0),
-                max_yaw_pitch_roll: Vec3::new(0.1, 0.1, 0.1),
-                trauma: 0.0,
-                trauma_power: 2.0,
-                decay: 0.8,
-                random_sources: [
-                    Box::new(RandomShake),
-                    Box::new(RandomShake),
-                    Box::new(RandomShake),
-                    Box::new(RandomShake),
-                    Box::new(RandomShake),
-                    Box::new(RandomShake),
-                ],
-            },
-            SpatialBundle::default()))
-            .id();
-
-        let transform;
-        #[cfg(feature = "debug")]
-        {
-            transform = Transform::from_xyz(-5.6, 2.7, 0.).looking_at(Vec3::new(0., 0.8, 0.), Vec3::Y);
-        }
-        #[cfg(not(feature = "debug"))]
-        {
-            transform = Transform::from_xyz(100., 250., -2.9).looking_at(Vec3::new(0., 0.8, 0.), Vec3::Y);
+        for player in 0..self.number_of_players.max(1) {
+            let shake_id = world 
+                .spawn((Shake3d {
+                    max_offset: Vec3::new(0.0, 0.0, 0.0),
+                    max_yaw_pitch_roll: Vec3::new(0.1, 0.1, 0.1),
+                    trauma: 0.0,
+                    trauma_power: 2.0,
+                    decay: 0.8,
+                    random_sources: [
+                        Box::new(RandomShake),
+                        Box::new(RandomShake),
+                        Box::new(RandomShake),
+                        Box::new(RandomShake),
+                        Box::new(RandomShake),
+                        Box::new(RandomShake),
+                    ],
+                },
+                SpatialBundle::default()))
+                .id();
+
+            let transform;
+            #[cfg(feature = "debug")]
+            {
+                transform = Transform::from_xyz(-5.6, 2.7, 0.).looking_at(Vec3::new(0., 0.8, 0.), Vec3::Y);
+            }
+            #[cfg(not(feature = "debug"))]
+            {
+                transform = Transform::from_xyz(100., 250., -2.9).looking_at(Vec3::new(0., 0.8, 0.), Vec3::Y);
+            }
+
+            let mut camera = 
+            world
+                .spawn((Camera3dBundle {
+                    transform,
+                    camera: Camera {
+                        order: player as isize,
+                        ..default()
+                    },
+                    camera_3d: Camera3d {
+                        // the first camera already cleared the whole window
+                        clear_color: if player == 0 { ClearColorConfig::Default } else { ClearColorConfig::None },
+                        ..default()
+                    },
+                    ..default()
+                },
+                PlayerCamera(player),
+                RenderLayers::default().with(player as u8 + 1),
+                self.cleanup_marker.clone(), ));
+
+            if split_screen {
+                // the HUD is drawn once across the whole window by the UI camera below
+                camera.insert(UiCameraConfig { show_ui: false });
+            }
+            let camera_id = camera.id();
+
+            if let Some(mut entity) = world.get_entity_mut(shake_id) {
+                entity.push_children(&[camera_id]);
+            }
         }
 
-        let camera_id = 
-        world
-            .spawn((Camera3dBundle {
-                transform,
+        if split_screen {
+            world.spawn((Camera2dBundle {
+                camera: Camera {
+                    order: self.number_of_players as isize,
+                    ..default()
+                },
+                camera_2d: Camera2d {
+                    clear_color: ClearColorConfig::None,
+                },
                 ..default()
-            },self.cleanup_marker, )).id();
-
-        if let Some(mut entity) = world.get_entity_mut(shake_id) {
-            entity.push_children(&[camera_id]);
+            }, self.cleanup_marker));
         }
     }
 }
@@ -1,7 +1,7 @@
 use bevy::{prelude::*, ecs::system::{Command, SystemState}, render::view::VisibleEntities, };
 use crate::{AppState, util};
 use bevy_xpbd_3d::{math::*, prelude::*};
-use super::{race, bullet, kart, player, assets, common::{self, health::Invulnerability}, config, game_settings};
+use super::{race, bullet, kart, player, assets, common::{self, health::Invulnerability}, config, tower::{weapons::Mine, TowerBody}};
 
 pub struct CollisionsPlugin;
 impl Plugin for CollisionsPlugin {
@@ -21,16 +21,19 @@ pub enum Layer {
 fn handle_collisions(
     mut commands: Commands,
     game_assets: Res<assets::GameAssets>,
-    mut game_state: ResMut<game_settings::GameState>,
     mut collision_event_reader: EventReader<Collision>,
     mut hit_event_writer: EventWriter<kart::HitEvent>,
     mut bullet_hit_event_writer: EventWriter<bullet::CreateHitEvent>,
     mut health_hit_event_writer: EventWriter<common::health::HealthHitEvent>,
+    mut race_bullet_hit_event_writer: EventWriter<race::events::BulletHit>,
+    game_config: Res<config::GameConfig>,
     visibile_entities: Query<&VisibleEntities, With<Camera>>,
     waypoints: Query<(Entity, &race::WayPoint)>,
     waypoint_trackers: Query<(Entity, &race::NextWayPoint)>,
     bullets: Query<(Entity, &bullet::Bullet, &Transform)>,
-    karts: Query<(Entity, &kart::Kart, Has<player::Player>, &kart::KartColor), Without<Invulnerability>>,
+    mines: Query<(Entity, &Mine, &Transform)>,
+    tower_bodies: Query<&TowerBody>,
+    karts: Query<(Entity, &kart::Kart, Option<&player::Player>, &kart::KartColor), Without<Invulnerability>>,
     tracks: Query<(Entity, With<super::Track>)>
 ) {
     for Collision(contacts) in collision_event_reader.read() {
@@ -63,18 +66,22 @@ fn handle_collisions(
                         entity: kart.0,
                         hit_points: 1
                     });
+                    race_bullet_hit_event_writer.send(race::events::BulletHit {
+                        owner: bullet.1.owner,
+                        target: kart.0,
+                    });
                     
                     if bullet_is_visible {
                         bullet_hit_event_writer.send(bullet::CreateHitEvent {
                             position: bullet.2.translation,
-                            count: config::BULLET_HIT_COUNT,
+                            count: game_config.bullet_hit_count,
                             material: game_assets.kart_colors[&kart.3.0].clone_weak(),
                             color: bullet.1.color,
                         });
                     }
 
-                    if kart.2 { // is player
-                        commands.add(util::screen_shake::CameraShake::default());
+                    if let Some(player) = kart.2 {
+                        commands.add(util::screen_shake::CameraShake::for_player(player.0));
                     }
                 }
             }
@@ -82,6 +89,78 @@ fn handle_collisions(
             _ => ()
         }
 
+        match (mines.get(contacts.entity1), karts.get(contacts.entity2),
+               mines.get(contacts.entity2), karts.get(contacts.entity1)) {
+            (Ok(mine), Ok(kart), _, _) | 
+            (_, _, Ok(mine), Ok(kart)) => {
+                if mine.1.owner != kart.0 {
+                    commands.entity(mine.0).despawn_recursive();
+                    health_hit_event_writer.send(common::health::HealthHitEvent {
+                        entity: kart.0,
+                        hit_points: game_config.mine_hit_points
+                    });
+                    bullet_hit_event_writer.send(bullet::CreateHitEvent {
+                        position: mine.2.translation,
+                        count: game_config.bullet_hit_count,
+                        material: game_assets.kart_colors[&kart.3.0].clone_weak(),
+                        color: mine.1.color,
+                    });
+
+                    if let Some(player) = kart.2 {
+                        commands.add(util::screen_shake::CameraShake::for_player(player.0));
+                    }
+                }
+            }
+
+            _ => ()
+        }
+
+        match (bullets.get(contacts.entity1), tower_bodies.get(contacts.entity2),
+               bullets.get(contacts.entity2), tower_bodies.get(contacts.entity1)) {
+            (Ok(bullet), Ok(tower_body), _, _) | 
+            (_, _, Ok(bullet), Ok(tower_body)) => {
+                if bullet.1.owner != tower_body.owner {
+                    let bullet_is_visible = visibile_entities.iter().any(|x| x.entities.contains(&bullet.0));
+                    commands.entity(bullet.0).despawn_recursive();
+                    health_hit_event_writer.send(common::health::HealthHitEvent {
+                        entity: tower_body.tower,
+                        hit_points: 1
+                    });
+                    race_bullet_hit_event_writer.send(race::events::BulletHit {
+                        owner: bullet.1.owner,
+                        target: tower_body.tower,
+                    });
+
+                    if bullet_is_visible {
+                        bullet_hit_event_writer.send(bullet::CreateHitEvent {
+                            position: bullet.2.translation,
+                            count: game_config.bullet_hit_count,
+                            material: bullet.1.material.clone_weak(),
+                            color: bullet.1.color,
+                        });
+                    }
+                }
+            }
+
+            _ => ()
+        }
+
+        // karts ramming into an enemy tower
+        match (tower_bodies.get(contacts.entity1), karts.get(contacts.entity2),
+               tower_bodies.get(contacts.entity2), karts.get(contacts.entity1)) {
+            (Ok(tower_body), Ok(kart), _, _) | 
+            (_, _, Ok(tower_body), Ok(kart)) => {
+                if tower_body.owner != kart.0 {
+                    health_hit_event_writer.send(common::health::HealthHitEvent {
+                        entity: tower_body.tower,
+                        hit_points: game_config.tower_ram_damage
+                    });
+                }
+            }
+
+            _ => ()
+        }
+
         // despawn bullets hitting track
         match (bullets.get(contacts.entity1), tracks.get(contacts.entity2),
                bullets.get(contacts.entity2), tracks.get(contacts.entity1)) {
@@ -91,7 +170,7 @@ fn handle_collisions(
                 if bullet_is_visible {
                     bullet_hit_event_writer.send(bullet::CreateHitEvent {
                         position: bullet.2.translation,
-                        count: config::BULLET_HIT_COUNT,
+                        count: game_config.bullet_hit_count,
                         material: bullet.1.material.clone_weak(),
                         color: bullet.1.color,
                     });
@@ -1,5 +1,5 @@
 use bevy::{prelude::*, ecs::system::{Command,SystemState}};
-use crate::{assets, ingame, AppState, ingame::player, ingame::kart};
+use crate::{assets, ingame, AppState, ingame::{camera, game_settings, player}};
 use bevy_xpbd_3d::PhysicsSet;
 use bevy::transform::TransformSystem;
 use bevy_kira_audio::prelude::*;
@@ -28,6 +28,10 @@ pub struct HealthHitEvent {
     pub hit_points: usize
 }
 
+/// Soaks up the next hit instead of the kart's health
+#[derive(Component)]
+pub struct Shield;
+
 #[derive(Component)]
 pub struct Invulnerability {
     time_to_live: Timer,
@@ -71,10 +75,15 @@ fn handle_invulnerability(
 fn handle_hit_events(
     mut commands: Commands,
     mut health_hit_event_reader: EventReader<HealthHitEvent>,
-    mut healths: Query<(Entity, &mut Health), Without<Invulnerability>>,
+    mut healths: Query<(Entity, &mut Health, Has<Shield>), Without<Invulnerability>>,
 ) {
     for event in health_hit_event_reader.read() {
-        if let Ok((entity, mut health)) = healths.get_mut(event.entity) {
+        if let Ok((entity, mut health, has_shield)) = healths.get_mut(event.entity) {
+            if has_shield {
+                commands.entity(entity).remove::<Shield>().insert(Invulnerability::default());
+                continue;
+            }
+
             #[cfg(not(feature = "endless"))]
             {
                 health.subtract(event.hit_points);
@@ -108,10 +117,12 @@ impl Health {
     }
 }
 
+/// Each player's camera gets its own copy of a health bar, facing it
 #[derive(Component)]
 pub struct HealthBar {
     pub parent: Entity,
     pub offset: Vec3,
+    pub player: usize,
 }
 
 fn scale_healthbars(
@@ -129,7 +140,7 @@ fn healthbar_follow_parent(
     mut commands: Commands,
     mut healthbars: Query<(Entity, &mut Transform, &HealthBar, Has<AudioReceiver>)>,
     audio: Res<Audio>,
-    parents: Query<&Transform, (With<kart::Kart>, Without<HealthBar>)>,
+    parents: Query<&Transform, (With<Health>, Without<HealthBar>)>,
 ) {
     for (entity, mut healthbar_transform, healthbar, has_receiver) in healthbars.iter_mut() {
         if let Ok(parent) = parents.get(healthbar.parent) {
@@ -143,12 +154,12 @@ fn healthbar_follow_parent(
     }
 }
 fn handle_healthbar_view(
-    mut healthbars: Query<&mut Transform, With<HealthBar>>,
-    camera: Query<&Transform, (With<Camera>, Without<HealthBar>)>,
+    mut healthbars: Query<(&mut Transform, &HealthBar)>,
+    cameras: Query<(&Transform, &camera::PlayerCamera), Without<HealthBar>>,
 ) {
-    if let Ok(camera) = camera.get_single() {
-        for mut healthbar in healthbars.iter_mut() {
-            healthbar.look_at(camera.translation, -Vec3::Y);
+    for (mut healthbar_transform, healthbar) in healthbars.iter_mut() {
+        if let Some((camera, _)) = cameras.iter().find(|(_, c)| c.0 == healthbar.player) {
+            healthbar_transform.look_at(camera.translation, -Vec3::Y);
         }
     }
 }
@@ -165,41 +176,48 @@ impl<C: Component + Clone> Command for HealthBarSpawner<C> {
         let mut system_state: SystemState<(
             assets::loader::AssetsHandler,
             Res<assets::GameAssets>,
-            Query<(&Transform, Has<player::Player>)>,
+            Res<game_settings::GameState>,
+            Query<(&Transform, Option<&player::Player>)>,
         )> = SystemState::new(world);
 
-        let (mut assets_handler, game_assets, transforms) = system_state.get_mut(world);
+        let (mut assets_handler, game_assets, game_state, transforms) = system_state.get_mut(world);
+        let number_of_cameras = game_state.number_of_players.max(1);
 
         let green_bar_material = assets_handler.materials.add(Color::GREEN.into()).clone();
         let mesh = game_assets.smoke.clone_weak(); 
 
-        if let Ok((transform, is_player)) = transforms.get(self.parent) {
+        if let Ok((transform, player)) = transforms.get(self.parent) {
+            // spatial audio only supports a single listener so the first player hears everything
+            let is_listener = matches!(player, Some(player::Player(0)));
             let mut transform = transform.clone();
             transform.translation += self.offset;
-            let mut healthbar= world.spawn( ( SpatialBundle::from_transform(transform), 
-                    HealthBar {
-                        parent: self.parent,
-                        offset: self.offset,
-                    },
-            ),);
-            let healthbar_id = healthbar.id();
-
-            if is_player {
-                healthbar.insert(AudioReceiver);
+            for camera_player in 0..number_of_cameras {
+                let mut healthbar= world.spawn( ( SpatialBundle::from_transform(transform), 
+                        HealthBar {
+                            parent: self.parent,
+                            offset: self.offset,
+                            player: camera_player,
+                        },
+                ),);
+
+                if is_listener && camera_player == 0 {
+                    healthbar.insert(AudioReceiver);
+                }
+                healthbar.with_children(|parent| {
+                        parent.spawn((PbrBundle {
+                            mesh: mesh.clone(),
+                            material: green_bar_material.clone(),
+                            transform: Transform::from_rotation(
+                                Quat::from_axis_angle(Vec3::X, (3.0 * std::f32::consts::PI) / 2.0))
+                                .with_scale(Vec3::new(HEALTHBAR_SIZE, 1.0, 0.3)),
+                            ..Default::default()
+                        },
+                        bevy::pbr::NotShadowCaster,
+                        camera::player_layer(camera_player),
+                        ingame::CleanupMarker,
+                    ));
+                });
             }
-            healthbar.with_children(|parent| {
-                    parent.spawn((PbrBundle {
-                        mesh ,
-                        material: green_bar_material.clone(),
-                        transform: Transform::from_rotation(
-                            Quat::from_axis_angle(Vec3::X, (3.0 * std::f32::consts::PI) / 2.0))
-                            .with_scale(Vec3::new(HEALTHBAR_SIZE, 1.0, 0.3)),
-                        ..Default::default()
-                    },
-                    bevy::pbr::NotShadowCaster,
-                    ingame::CleanupMarker,
-                ));
-            });
 
 
             if let Some(mut parent) = world.get_entity_mut(self.parent) {
@@ -1,17 +1,265 @@
-pub const BASE_ACCELERATION: f32 = 27.0;
-pub const BASE_DECELERATION: f32 = 0.25;
-pub const MOVEMENT_DAMPING: f32 = 59.;
-pub const ROTATION_DAMPING: f32 = 0.07;
-pub const MAX_SLOPE: f32 = 0.35;
-pub const TRACK_WIDTH: f32 = 10.0;
-pub const TOWER_HEIGHT: f32 = 10.0;
-pub const TOWER_POSITION_BUFFER: f32 = 1.0;
-pub const NUMBER_OF_PLAYERS: usize = 1; // TODO: Move this to Game Settings
-pub const HIT_SHRINK_SPEED: f32 = 5.0;
-pub const HIT_SPEED: f32 = 9.0;
-pub const KART_HEALTH: usize = 5;
-pub const BULLET_HIT_COUNT: usize = 6;
-pub const KART_DIE_HIT_COUNT: usize = 12;
-pub const AUDIO_DISTANCE: f32 = 20.;
-pub const SHRAPNEL_TIME_TO_LIVE: f32 = 5.;
-pub const GRAVITY_FORCE: f32 = 9.81 * 15.0;
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    reflect::TypePath,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+use crate::{assets, AppState};
+
+pub const MAX_NUMBER_OF_PLAYERS: usize = 4;
+
+pub struct ConfigPlugin;
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<GameConfig>()
+            .init_asset_loader::<GameConfigLoader>()
+            .init_resource::<GameConfig>()
+            .add_event::<GameConfigReloaded>()
+            .add_systems(Update, reload_game_config.run_if(in_state(AppState::InGame)));
+    }
+}
+
+/// Tuning values read from `assets/game.config.ron`. The defaults match the
+/// shipped file and are only used until it has been loaded.
+#[derive(Asset, Resource, TypePath, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    pub base_acceleration: f32,
+    pub base_deceleration: f32,
+    pub movement_damping: f32,
+    pub rotation_damping: f32,
+    pub max_slope: f32,
+    pub gravity_force: f32,
+    pub track_width: f32,
+    pub tower_height: f32,
+    pub tower_position_buffer: f32,
+    pub cannon_cost: usize,
+    pub tower_action_cooldown: f32,
+    pub bullet_speed: f32,
+    pub tower_health: usize,
+    pub tower_ram_damage: usize,
+    /// Laps the leading kart completes before a tower crumbles, 0 keeps towers forever
+    pub tower_lifetime_laps: usize,
+    /// Upgrading costs this times the tower's current level
+    pub tower_upgrade_cost: usize,
+    pub tower_upgrade_range: f32,
+    pub tower_max_level: usize,
+    pub turret_cost: usize,
+    pub turret_cooldown: f32,
+    pub turret_range: f32,
+    pub slow_field_cost: usize,
+    pub slow_field_cooldown: f32,
+    pub slow_field_radius: f32,
+    pub slow_field_factor: f32,
+    pub mine_cost: usize,
+    pub mine_cooldown: f32,
+    pub mine_limit: usize,
+    pub mine_hit_points: usize,
+    pub barrier_cost: usize,
+    pub barrier_cooldown: f32,
+    pub hit_shrink_speed: f32,
+    pub hit_speed: f32,
+    pub bullet_hit_count: usize,
+    pub kart_die_hit_count: usize,
+    pub audio_distance: f32,
+    /// Seconds a kart has to drive against the track before it's warned
+    pub wrong_way_time: f32,
+    /// Share of the track a kart has to drive along for its lap to count
+    pub min_lap_fraction: f32,
+    /// Karts further than this from the racing line are off the track
+    pub off_track_distance: f32,
+    /// Share of a lap that can be driven off the track before it stops counting
+    pub max_off_track_fraction: f32,
+    pub lap_penalty: LapPenalty,
+    /// Seconds held in place, credits lost or hit points taken for a rejected lap
+    pub lap_penalty_amount: usize,
+    /// Seconds the rest of the field has to finish once the winner crosses the line
+    pub finish_timeout: f32,
+    /// Seconds before a picked up item box comes back
+    pub item_box_respawn_time: f32,
+    pub boost_time: f32,
+    pub boost_acceleration: f32,
+    /// Hit points given back by a repair
+    pub repair_amount: usize,
+    pub credit_bundle: usize,
+    /// Karts slower than this can't start or keep a drift going
+    pub drift_min_speed: f32,
+    /// How much sharper a kart turns while drifting
+    pub drift_turn_factor: f32,
+    /// Seconds of drifting per mini-turbo tier
+    pub drift_tier_time: f32,
+    /// Seconds of boost given for each mini-turbo tier
+    pub drift_boost_time: f32,
+    pub light_kart: KartStats,
+    pub balanced_kart: KartStats,
+    pub heavy_kart: KartStats,
+}
+
+/// Handling for one of the kart classes, everything but health scales the base values above
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct KartStats {
+    pub acceleration: f32,
+    pub turning: f32,
+    pub health: usize,
+    /// Scales the kart's collider
+    pub size: f32,
+    pub tower_cost: f32,
+}
+
+impl Default for KartStats {
+    fn default() -> Self {
+        KartStats {
+            acceleration: 1.0,
+            turning: 1.0,
+            health: 5,
+            size: 1.0,
+            tower_cost: 1.0,
+        }
+    }
+}
+
+impl KartStats {
+    pub fn tower_cost(&self, cost: usize) -> usize {
+        (cost as f32 * self.tower_cost).round() as usize
+    }
+}
+
+/// What a kart loses when one of its laps is rejected for cutting the track
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub enum LapPenalty {
+    Time,
+    #[default]
+    Credits,
+    Health,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            base_acceleration: 27.0,
+            base_deceleration: 0.25,
+            movement_damping: 59.,
+            rotation_damping: 0.07,
+            max_slope: 0.35,
+            gravity_force: 9.81 * 15.0,
+            track_width: 10.0,
+            tower_height: 10.0,
+            tower_position_buffer: 1.0,
+            cannon_cost: 4,
+            tower_action_cooldown: 0.5,
+            bullet_speed: 2.0,
+            tower_health: 4,
+            tower_ram_damage: 1,
+            tower_lifetime_laps: 0,
+            tower_upgrade_cost: 3,
+            tower_upgrade_range: 20.0,
+            tower_max_level: 3,
+            turret_cost: 6,
+            turret_cooldown: 1.0,
+            turret_range: 40.0,
+            slow_field_cost: 5,
+            slow_field_cooldown: 0.25,
+            slow_field_radius: 12.0,
+            slow_field_factor: 0.4,
+            mine_cost: 3,
+            mine_cooldown: 4.0,
+            mine_limit: 3,
+            mine_hit_points: 2,
+            barrier_cost: 4,
+            barrier_cooldown: 3.0,
+            hit_shrink_speed: 5.0,
+            hit_speed: 9.0,
+            bullet_hit_count: 6,
+            kart_die_hit_count: 12,
+            audio_distance: 20.,
+            wrong_way_time: 1.0,
+            min_lap_fraction: 0.85,
+            off_track_distance: 25.,
+            max_off_track_fraction: 0.2,
+            lap_penalty: LapPenalty::Credits,
+            lap_penalty_amount: 5,
+            finish_timeout: 30.,
+            item_box_respawn_time: 8.,
+            boost_time: 1.5,
+            boost_acceleration: 40.,
+            repair_amount: 2,
+            credit_bundle: 4,
+            drift_min_speed: 12.,
+            drift_turn_factor: 1.6,
+            drift_tier_time: 0.8,
+            drift_boost_time: 0.4,
+            light_kart: KartStats {
+                acceleration: 1.1,
+                turning: 1.2,
+                health: 4,
+                size: 0.85,
+                tower_cost: 0.75,
+            },
+            balanced_kart: KartStats::default(),
+            heavy_kart: KartStats {
+                acceleration: 0.9,
+                turning: 0.85,
+                health: 7,
+                size: 1.2,
+                tower_cost: 1.25,
+            },
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct GameConfigLoader;
+impl AssetLoader for GameConfigLoader {
+    type Asset = GameConfig;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes::<GameConfig>(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["config.ron"]
+    }
+}
+
+/// Copies the loaded config file into the [`GameConfig`] resource
+pub fn apply_game_config(
+    mut game_config: ResMut<GameConfig>,
+    game_assets: Res<assets::GameAssets>,
+    configs: Res<Assets<GameConfig>>,
+) {
+    if let Some(loaded) = configs.get(&game_assets.game_config) {
+        *game_config = loaded.clone();
+    }
+}
+
+/// Sent after the config file was edited and the [`GameConfig`] resource updated mid-race
+#[derive(Event)]
+pub struct GameConfigReloaded;
+
+fn reload_game_config(
+    mut asset_events: EventReader<AssetEvent<GameConfig>>,
+    mut reloaded_events: EventWriter<GameConfigReloaded>,
+    game_config: ResMut<GameConfig>,
+    game_assets: Res<assets::GameAssets>,
+    configs: Res<Assets<GameConfig>>,
+) {
+    let modified = asset_events.read().any(|event| event.is_modified(&game_assets.game_config));
+    if modified {
+        info!("reloading game config");
+        apply_game_config(game_config, game_assets, configs);
+        reloaded_events.send(GameConfigReloaded);
+    }
+}
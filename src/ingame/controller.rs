@@ -1,24 +1,29 @@
 // Adapted from bevy_xpbd_3d 🙏🙏🙏🙏🙏  
-use bevy::{ecs::query::Has, prelude::*};
+use bevy::{ecs::query::Has, prelude::*, utils::HashMap};
 use bevy_xpbd_3d::{math::*, prelude::*, SubstepSchedule, SubstepSet};
-use crate::{ingame::kart, ingame::assets, ingame::player, ingame::config, ingame::tower, AppState, IngameState, ingame::path,};
-use bevy::input::gamepad::GamepadButtonType;
+use crate::{util::input, ingame::kart, ingame::assets, ingame::player, ingame::config, ingame::tower, ingame::items, AppState, IngameState, ingame::path,};
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
 
 pub struct CharacterControllerPlugin;
 
 impl Plugin for CharacterControllerPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<MovementEvent>()
+            .add_event::<KartActionEvent>()
             .add_systems(
                 Update,
                 (
-                    (keyboard_input,
-                    gamepad_input,).run_if(in_state(AppState::InGame).and_then(in_state(IngameState::InGame))),
+                    player_input
+                        .in_set(ControllerSet::Input)
+                        .run_if(in_state(AppState::InGame).and_then(in_state(IngameState::InGame))),
                     update_grounded,
                     handle_fallen,
+                    apply_kart_actions.in_set(ControllerSet::Movement),
                     apply_deferred,
                     apply_gravity,
-                    movement,
+                    movement.in_set(ControllerSet::Movement),
+                    drift.run_if(in_state(IngameState::InGame)),
                     apply_movement_damping,
                 )
                     .chain(),
@@ -27,10 +32,19 @@ impl Plugin for CharacterControllerPlugin {
                 // Run collision handling in substep schedule
                 SubstepSchedule,
                 kinematic_controller_collisions.in_set(SubstepSet::SolveUserConstraints),
-            );
+            )
+            .add_systems(Update, apply_game_config.run_if(on_event::<config::GameConfigReloaded>()));
     }
 }
 
+/// Lets other modules feed or observe [`MovementEvent`]s and [`KartActionEvent`]s
+/// between input and when they're applied.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ControllerSet {
+    Input,
+    Movement,
+}
+
 /// An event sent for a movement input action.
 #[derive(Event)]
 pub struct MovementEvent {
@@ -38,18 +52,31 @@ pub struct MovementEvent {
     pub action: MovementAction,
 }
 
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum MovementAction {
     Gas,
     Brake,
     Turn(f32),
 }
 
-#[derive(Component)]
-pub struct CommonController;
+/// Something a kart does besides driving. Players and bots send these and they're
+/// all carried out by `apply_kart_actions`, in the order they were sent, so a
+/// replay can feed them back in at the same point of the frame.
+#[derive(Event)]
+pub struct KartActionEvent {
+    pub entity: Entity,
+    pub action: KartAction,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum KartAction {
+    PlaceTower(tower::TowerKind),
+    UpgradeTower,
+    UseItem,
+}
 
-/// A marker component indicating that an entity is using the keyboard
 #[derive(Component)]
-pub struct CharacterControllerKeyboard;
+pub struct CommonController;
 
 /// A marker component indicating that an entity is on the ground.
 #[derive(Component)]
@@ -66,6 +93,23 @@ pub struct LastGrounded {
 #[derive(Component)]
 #[component(storage = "SparseSet")]
 pub struct Braking;
+
+/// Highest mini-turbo a drift can charge up to
+pub const MAX_DRIFT_TIER: usize = 3;
+
+/// Lets a kart drift by braking into a turn. Only players get this since bots
+/// brake through every corner.
+#[derive(Component)]
+pub struct Drifter;
+
+/// A kart sliding through a corner. It turns tighter and charges a mini-turbo
+/// that's released when the drift ends.
+#[derive(Component, Default)]
+#[component(storage = "SparseSet")]
+pub struct Drifting {
+    charge: f32,
+    pub tier: usize,
+}
 /// The acceleration used for character movement.
 #[derive(Component)]
 pub struct MovementAcceleration(pub Scalar);
@@ -134,7 +178,8 @@ impl MovementBundle {
 
 impl Default for MovementBundle {
     fn default() -> Self {
-        Self::new(config::BASE_ACCELERATION, config::BASE_DECELERATION, config::MOVEMENT_DAMPING, config::ROTATION_DAMPING,  PI * config::MAX_SLOPE)
+        let config = config::GameConfig::default();
+        Self::new(config.base_acceleration, config.base_deceleration, config.movement_damping, config.rotation_damping,  PI * config.max_slope)
     }
 }
 
@@ -173,102 +218,100 @@ impl CommonControllerBundle {
     }
 }
 
-/// Sends [`MovementEvent`] events based on keyboard input.
-fn keyboard_input(
-    mut commands: Commands,
+/// Updates existing controllers when the [`config::GameConfig`] is reloaded.
+fn apply_game_config(
+    game_config: Res<config::GameConfig>,
+    mut controllers: Query<(
+        &mut MovementAcceleration,
+        &mut MovementDeceleration,
+        &mut MovementDampingFactor,
+        &mut RotationDampingFactor,
+        &mut MaxSlopeAngle,
+        &mut ControllerGravity,
+        Option<&kart::KartClass>,
+        Has<Drifting>,
+        Has<tower::weapons::Slowed>,
+    )>,
+) {
+    // karts that are slowed or drifting keep those modifiers on top of the new values
+    for (mut acceleration, mut deceleration, mut damping, mut rotation_damping, mut max_slope_angle, mut gravity, class, is_drifting, is_slowed) in &mut controllers {
+        let stats = class.copied().unwrap_or_default().stats(&game_config);
+        acceleration.0 = game_config.base_acceleration * stats.acceleration * if is_slowed { game_config.slow_field_factor } else { 1. };
+        deceleration.0 = game_config.base_deceleration;
+        damping.0 = game_config.movement_damping;
+        rotation_damping.0 = game_config.rotation_damping * stats.turning * if is_drifting { game_config.drift_turn_factor } else { 1. };
+        max_slope_angle.0 = PI * game_config.max_slope;
+        gravity.0 = Vector::NEG_Y * game_config.gravity_force;
+    }
+}
+
+/// Sends [`MovementEvent`] and [`KartActionEvent`] events based on each player's [`input::GameAction`]s.
+fn player_input(
     mut movement_event_writer: EventWriter<MovementEvent>,
-    game_assets: Res<assets::GameAssets>,
-    keyboard_input: Res<Input<KeyCode>>,
-    keyboard_player: Query<(Entity, &kart::KartColor,), With<CharacterControllerKeyboard>>,
+    mut kart_action_event_writer: EventWriter<KartActionEvent>,
+    mut players: Query<(Entity, &ActionState<input::GameAction>, &mut tower::SelectedTower)>,
 ) {
-    for (entity, kart_color, ) in &keyboard_player {
-        let up = keyboard_input.any_pressed([KeyCode::W, KeyCode::Z, KeyCode::Up]);
-        let down = keyboard_input.any_pressed([KeyCode::S, KeyCode::Down]);
-        let left = keyboard_input.any_pressed([KeyCode::A, KeyCode::Q, KeyCode::Left]);
-        let right = keyboard_input.any_pressed([KeyCode::D, KeyCode::Right]);
+    for (entity, action_state, mut selected_tower) in &mut players {
+        if action_state.just_pressed(input::GameAction::PlaceTower) {
+            kart_action_event_writer.send(KartActionEvent { entity, action: KartAction::PlaceTower(selected_tower.0) });
+        }
 
-        let right_trigger = keyboard_input.just_pressed(KeyCode::Space);
-        let left_trigger = keyboard_input.just_pressed(KeyCode::H);
+        if action_state.just_pressed(input::GameAction::UpgradeTower) {
+            kart_action_event_writer.send(KartActionEvent { entity, action: KartAction::UpgradeTower });
+        }
 
-        if right_trigger {
-            commands.add(tower::TowerSpawner { entity, material: game_assets.kart_colors[&kart_color.0].clone_weak() });
+        if action_state.just_pressed(input::GameAction::UseItem) {
+            kart_action_event_writer.send(KartActionEvent { entity, action: KartAction::UseItem });
         }
 
-        if up {
+        if action_state.just_pressed(input::GameAction::CycleTower) {
+            selected_tower.0 = selected_tower.0.next();
+        }
+        if action_state.just_pressed(input::GameAction::PreviousTower) {
+            selected_tower.0 = selected_tower.0.previous();
+        }
+
+        if action_state.pressed(input::GameAction::Gas) {
             movement_event_writer.send(MovementEvent {
                 entity,
                 action: MovementAction::Gas,
             });
         }
-        if down {
+
+        if action_state.pressed(input::GameAction::Brake) {
             movement_event_writer.send(MovementEvent {
                 entity,
                 action: MovementAction::Brake,
             });
         }
-        if left {
-            movement_event_writer.send(MovementEvent {
-                entity,
-                action: MovementAction::Turn(0.8),
-            });
-        } else if right {
+
+        let steer = action_state.value(input::GameAction::Steer);
+        if steer != 0. {
             movement_event_writer.send(MovementEvent {
                 entity,
-                action: MovementAction::Turn(-0.8),
+                action: MovementAction::Turn((-steer * input::KEYBOARD_STEER).clamp(-1., 1.)),
             });
         }
     }
 }
 
-/// Sends [`MovementEvent`] events based on gamepad input.
-fn gamepad_input(
+/// Carries out every [`KartActionEvent`] sent this frame
+fn apply_kart_actions(
     mut commands: Commands,
-    mut movement_event_writer: EventWriter<MovementEvent>,
-    keyboard_player: Query<(Entity, &kart::KartColor), With<CharacterControllerKeyboard>>,
+    mut kart_action_event_reader: EventReader<KartActionEvent>,
     game_assets: Res<assets::GameAssets>,
-    gamepads: Res<Gamepads>,
-    axes: Res<Axis<GamepadAxis>>,
-    buttons: Res<Input<GamepadButton>>,
+    karts: Query<&kart::KartColor>,
 ) {
-    // TODO: refactor this for multiplayer?
-    for (entity, kart_color) in &keyboard_player {
-        for gamepad in gamepads.iter() {
-            let axis_lx = GamepadAxis {
-                gamepad,
-                axis_type: GamepadAxisType::LeftStickX,
-            };
-            let axis_ly = GamepadAxis {
-                gamepad,
-                axis_type: GamepadAxisType::LeftStickY,
-            };
-
-
-            if buttons.just_pressed(GamepadButton { gamepad,  button_type: GamepadButtonType::West }) ||
-                buttons.just_pressed(GamepadButton { gamepad,  button_type: GamepadButtonType::North }) || 
-                buttons.just_pressed(GamepadButton { gamepad,  button_type: GamepadButtonType::LeftTrigger }) {
-                commands.add(tower::TowerSpawner { entity, material: game_assets.kart_colors[&kart_color.0].clone_weak() });
-            }
-
-            if buttons.pressed(GamepadButton { gamepad,  button_type: GamepadButtonType::South }) {
-                movement_event_writer.send(MovementEvent {
-                    entity,
-                    action: MovementAction::Gas,
-                });
-            }
-
-            if buttons.pressed(GamepadButton { gamepad,  button_type: GamepadButtonType::East }) 
-            || buttons.pressed(GamepadButton { gamepad,  button_type: GamepadButtonType::RightTrigger }) {
-                movement_event_writer.send(MovementEvent {
-                    entity,
-                    action: MovementAction::Brake,
-                });
-            }
-            if let (Some(x), Some(y)) = (axes.get(axis_lx), axes.get(axis_ly)) {
-                movement_event_writer.send(MovementEvent {
-                    entity,
-                    action: MovementAction::Turn(-x),
-                });
-            }
+    for event in kart_action_event_reader.read() {
+        let entity = event.entity;
+        match event.action {
+            KartAction::PlaceTower(kind) => {
+                if let Ok(kart_color) = karts.get(entity) {
+                    commands.add(tower::TowerSpawner { entity, material: game_assets.kart_colors[&kart_color.0].clone_weak(), kind });
+                }
+            },
+            KartAction::UpgradeTower => commands.add(tower::TowerUpgrader { entity }),
+            KartAction::UseItem => commands.add(items::ItemUser { entity }),
         }
     }
 }
@@ -282,11 +325,16 @@ fn handle_fallen(
     for (entity, mut transform, mut last_grounded) in &mut query {
         last_grounded.delta += time.delta_seconds();
         if last_grounded.delta > 2. {
-            transform.translation = path_manager.get_closest_index(last_grounded.translation)
-                                        .and_then(|i| path_manager.get_next(i))
-                                        .map(|i| path_manager.get(i))
-                                        .map(|v| v + (Vec3::Y * 3.))
-                                        .unwrap_or(last_grounded.translation);
+            if path_manager.is_empty() {
+                transform.translation = last_grounded.translation;
+            } else {
+                let progress = path_manager.progress_at(last_grounded.translation) + path_manager.point_spacing();
+                let heading = path_manager.tangent_at(progress) * Vec3::new(1., 0., 1.);
+                transform.translation = path_manager.point_at(progress) + (Vec3::Y * 3.);
+                if heading != Vec3::ZERO {
+                    transform.look_to(heading, Vec3::Y);
+                }
+            }
             commands.entity(entity).remove::<LastGrounded>();
         }
     }
@@ -339,12 +387,13 @@ fn movement(
         &mut LinearVelocity,
         &mut Transform,
         Has<Grounded>,
+        Has<Drifting>,
     )>,
 ) {
     let delta_time = time.delta_seconds();
 
     for event in movement_event_reader.read() {
-        if let Ok((entity, movement_acceleration, movement_deceleration, rotation_damping, mut linear_velocity, mut transform, is_grounded)) = controllers.get_mut(event.entity) {
+        if let Ok((entity, movement_acceleration, movement_deceleration, rotation_damping, mut linear_velocity, mut transform, is_grounded, is_drifting)) = controllers.get_mut(event.entity) {
             match event.action {
                 MovementAction::Gas => {
                     commands.entity(entity).remove::<Braking>();
@@ -354,6 +403,10 @@ fn movement(
                 },
                 MovementAction::Brake => {
                     commands.entity(entity).insert(Braking);
+                    // the brake is held to keep drifting so it doesn't slow the kart down
+                    if is_drifting {
+                        continue;
+                    }
                     let direction = transform.forward(); 
                     linear_velocity.x -= direction.x * (movement_acceleration.0 * movement_deceleration.0) * delta_time;
                     linear_velocity.z -= direction.z * (movement_acceleration.0 * movement_deceleration.0) * delta_time;
@@ -367,6 +420,51 @@ fn movement(
     }
 }
 
+/// Starts a [`Drifting`] state when a [`Drifter`] brakes into a turn at speed, charges
+/// it for as long as the brake is held and lets the charge go as a boost afterwards.
+fn drift(
+    mut commands: Commands,
+    mut movement_event_reader: EventReader<MovementEvent>,
+    mut drifters: Query<(Entity, &mut RotationDampingFactor, &LinearVelocity, &kart::KartClass, Option<&mut Drifting>, Has<Grounded>), With<Drifter>>,
+    game_config: Res<config::GameConfig>,
+    time: Res<Time>,
+) {
+    let mut inputs: HashMap<Entity, (bool, bool)> = HashMap::new();
+    for event in movement_event_reader.read() {
+        let (is_braking, is_turning) = inputs.entry(event.entity).or_default();
+        match event.action {
+            MovementAction::Brake => *is_braking = true,
+            MovementAction::Turn(_) => *is_turning = true,
+            MovementAction::Gas => (),
+        }
+    }
+
+    for (entity, mut rotation_damping, linear_velocity, class, drifting, is_grounded) in &mut drifters {
+        let (is_braking, is_turning) = inputs.get(&entity).copied().unwrap_or_default();
+        let turning = game_config.rotation_damping * class.stats(&game_config).turning;
+        let is_fast = (linear_velocity.0 * Vec3::new(1., 0., 1.)).length() > game_config.drift_min_speed;
+
+        match drifting {
+            Some(mut drifting) if is_braking && is_grounded && is_fast => {
+                drifting.charge += time.delta_seconds();
+                drifting.tier = ((drifting.charge / game_config.drift_tier_time) as usize).min(MAX_DRIFT_TIER);
+            },
+            Some(drifting) => {
+                rotation_damping.0 = turning;
+                commands.entity(entity).remove::<Drifting>();
+                if drifting.tier > 0 && is_grounded {
+                    commands.entity(entity).insert(items::SpeedBoost::new(game_config.drift_boost_time * drifting.tier as f32));
+                }
+            },
+            None if is_braking && is_turning && is_grounded && is_fast => {
+                rotation_damping.0 = turning * game_config.drift_turn_factor;
+                commands.entity(entity).insert(Drifting::default());
+            },
+            None => (),
+        }
+    }
+}
+
 /// Applies [`ControllerGravity`] to character controllers.
 fn apply_gravity(
     time: Res<Time>,
@@ -404,6 +502,7 @@ pub fn apply_movement_damping(time: Res<Time>, mut query: Query<(&MovementDampin
 fn kinematic_controller_collisions(
     collisions: Res<Collisions>,
     collider_parents: Query<&ColliderParent, Without<Sensor>>,
+    barriers: Query<&tower::weapons::Barrier>,
     mut character_controllers: Query<
         (
             &RigidBody,
@@ -429,6 +528,16 @@ fn kinematic_controller_collisions(
             continue;
         };
 
+        // Karts drive through their own barriers
+        let (kart, other) = if character_controllers.contains(collider_parent1.get()) {
+            (collider_parent1.get(), collider_parent2.get())
+        } else {
+            (collider_parent2.get(), collider_parent1.get())
+        };
+        if barriers.get(other).is_ok_and(|barrier| !barrier.blocks(kart)) {
+            continue;
+        }
+
         // Get the body of the character controller and whether it is the first
         // or second entity in the collision.
         let is_first: bool;
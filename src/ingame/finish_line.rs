@@ -13,7 +13,7 @@ impl Plugin for FinishLinePlugin {
    should make 4 invisible walls for start, quarter, halfway, 3/4ths, and then have a detector  
    each kart should track where the next wall they need to hit, like an enum
    when a hit is detected, change the enum on the kart for their next target
-   we might also be able to detect when a kart is going backward by seeing if they hit a wall they shouldn't
+   going backward and cutting the track are caught in race::lap_check instead
    when the kart crosses the finish line after crossing the other walls, increment their lap count
 
 */
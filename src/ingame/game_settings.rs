@@ -1,5 +1,10 @@
 use bevy::prelude::*;
-use crate::{ingame::player, ingame::race::placement_sensor::Place, ingame::race::LapCounter};
+use serde::{Deserialize, Serialize};
+use crate::{ingame::bot, ingame::kart, ingame::player, ingame::track, ingame::race::placement::Place, ingame::race::LapCounter};
+
+pub const DEFAULT_PLAYER_NAME: &str = "Player 1";
+/// The last of the `kart_colors`, which the first player always got before colors could be picked
+pub const DEFAULT_PLAYER_COLOR: usize = 7;
 
 #[derive(Resource)]
 pub struct GameState {
@@ -14,29 +19,62 @@ pub struct GameState {
     pub player_place: usize,
     pub player_lap: usize,
     pub controller_type: ControllerType,
+    pub number_of_players: usize,
+    pub track: usize,
+    pub time_trial: bool,
+    pub bot_difficulty: bot::Difficulty,
+    /// The class every player races with, bots pick their own
+    pub kart_class: kart::KartClass,
+    /// Name and color the first player picked, the color is an index into the default `kart_colors`
+    pub player_name: String,
+    pub player_color: usize,
+    /// Overrides the track's lap target when set
+    pub laps: Option<usize>,
 }
 
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum ControllerType {
     Keyboard,
     Gamepad,
 }
 
-#[derive(Default, PartialEq)]
+#[derive(Clone, Copy, Default, PartialEq)]
 pub enum GameEndingState {
     Winner,
     Died,
     FellBehind,
+    Finished,
     #[default]
     Initial,
 }
 
 impl GameState {
-    pub fn initialize(enable_shadows: bool, enable_background: bool, controller_type: ControllerType) -> Self {
+    pub fn initialize(enable_shadows: bool, enable_background: bool, controller_type: ControllerType, number_of_players: usize) -> Self {
         GameState {
-            enable_shadows, enable_background, controller_type,
+            enable_shadows, enable_background, controller_type, number_of_players,
             ..default()
         }
     }
+
+    /// The same race again, with everything the last one used up put back
+    pub fn for_restart(&self) -> Self {
+        GameState {
+            track: self.track,
+            time_trial: self.time_trial,
+            laps: self.laps,
+            bot_difficulty: self.bot_difficulty,
+            kart_class: self.kart_class,
+            player_name: self.player_name.clone(),
+            player_color: self.player_color,
+            ..GameState::initialize(self.enable_shadows, self.enable_background, self.controller_type, self.number_of_players)
+        }
+    }
+
+    /// Laps needed to finish the race, `None` races until a single kart is left
+    pub fn race_laps(&self) -> Option<usize> {
+        let track = track::get(self.track);
+        if self.time_trial { Some(track.time_trial_laps()) } else { self.laps.or(track.laps) }
+    }
 }
 
 impl Default for GameState {
@@ -62,6 +100,14 @@ impl Default for GameState {
             player_place: 0,
             player_lap: 0,
             controller_type: ControllerType::Keyboard,
+            number_of_players: 1,
+            track: 0,
+            time_trial: false,
+            bot_difficulty: bot::Difficulty::default(),
+            kart_class: kart::KartClass::default(),
+            player_name: DEFAULT_PLAYER_NAME.to_string(),
+            player_color: DEFAULT_PLAYER_COLOR,
+            laps: None,
         }
     }
 }
@@ -69,14 +115,16 @@ impl Default for GameState {
 pub fn update_game_state(
     mut game_state: ResMut<GameState>,
     entities: Query<Entity>,
-    player: Query<(&Place, &LapCounter), With<player::Player>>,
+    player: Query<(&player::Player, &Place, &LapCounter)>,
     time: Res<Time>
 ) {
     game_state.peak_number_of_entities = game_state.peak_number_of_entities.max(entities.iter().len());
     game_state.game_time += time.delta_seconds();
 
-    for (place, lap_counter) in &player {
-        game_state.player_place = place.0; 
-        game_state.player_lap = lap_counter.0;
+    for (player, place, lap_counter) in &player {
+        if player.0 == 0 {
+            game_state.player_place = place.0; 
+            game_state.player_lap = lap_counter.0;
+        }
     }
 }
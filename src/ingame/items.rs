@@ -0,0 +1,241 @@
+use bevy::{prelude::*, ecs::system::Command};
+use bevy_turborand::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+use crate::{AppState, IngameState};
+use super::{bot, common, config, controller, points, race, race::time_trial};
+
+/// Radians per second the item boxes turn while waiting to be picked up
+const SPIN_SPEED: f32 = 1.5;
+
+pub struct ItemsPlugin;
+impl Plugin for ItemsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+                Update,
+                (
+                    (pick_up_items, respawn_item_boxes).run_if(not(time_trial::is_time_trial)),
+                    use_bot_items.in_set(bot::BotSet),
+                    boost_karts.after(controller::ControllerSet::Movement),
+                ).run_if(in_state(AppState::InGame).and_then(in_state(IngameState::InGame))),
+            )
+            .add_systems(Update, spin_item_boxes.run_if(in_state(AppState::InGame)));
+    }
+}
+
+/// A box on the track, authored as an `item_box` node in the track's glb, that
+/// hands a power-up to the first kart through it and then comes back after a while
+#[derive(Component)]
+pub struct ItemBox {
+    respawn: Timer,
+}
+
+impl Default for ItemBox {
+    fn default() -> Self {
+        ItemBox {
+            respawn: Timer::from_seconds(0., TimerMode::Once),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PowerUp {
+    SpeedBoost,
+    Shield,
+    Repair,
+    Credits,
+    FreeTower,
+}
+
+/// How likely each power-up is for the kart in the lead and for the kart in last,
+/// everyone in between gets a mix of the two
+const WEIGHTS: [(PowerUp, f32, f32); 5] = [
+    (PowerUp::SpeedBoost, 1.0, 4.0),
+    (PowerUp::Shield, 2.0, 2.0),
+    (PowerUp::Repair, 2.0, 1.0),
+    (PowerUp::Credits, 4.0, 2.0),
+    (PowerUp::FreeTower, 0.5, 3.0),
+];
+
+impl PowerUp {
+    pub fn label(&self) -> &str {
+        match self {
+            PowerUp::SpeedBoost => "Boost",
+            PowerUp::Shield => "Shield",
+            PowerUp::Repair => "Repair",
+            PowerUp::Credits => "Credits",
+            PowerUp::FreeTower => "Free Tower",
+        }
+    }
+
+    /// `behind` is 0 for the leader and 1 for last place, `random` is between 0 and 1
+    fn roll(behind: f32, random: f32) -> PowerUp {
+        let weights = WEIGHTS.map(|(power_up, front, back)| (power_up, front + (back - front) * behind));
+        let mut pick = random * weights.iter().map(|(_, weight)| weight).sum::<f32>();
+        for (power_up, weight) in weights {
+            if pick < weight {
+                return power_up;
+            }
+            pick -= weight;
+        }
+
+        weights[weights.len() - 1].0
+    }
+}
+
+/// The power-up a kart is carrying, karts can only hold one at a time
+#[derive(Component, Default)]
+pub struct HeldItem(pub Option<PowerUp>);
+
+/// Pushes the kart forward until the timer runs out
+#[derive(Component)]
+pub struct SpeedBoost(Timer);
+
+impl SpeedBoost {
+    pub fn new(seconds: f32) -> Self {
+        SpeedBoost(Timer::from_seconds(seconds, TimerMode::Once))
+    }
+}
+
+/// The kart's next tower costs nothing
+#[derive(Component)]
+pub struct FreeTower;
+
+/// Uses whatever the kart is holding. Added for [`controller::KartAction::UseItem`],
+/// which bots send straight after a pickup.
+pub struct ItemUser {
+    pub entity: Entity,
+}
+impl Command for ItemUser {
+    fn apply(self, world: &mut World) {
+        let game_config = world.resource::<config::GameConfig>();
+        let (boost_time, repair_amount, credit_bundle) = (game_config.boost_time, game_config.repair_amount, game_config.credit_bundle);
+
+        let Some(mut kart) = world.get_entity_mut(self.entity) else { return };
+        let Some(power_up) = kart.get_mut::<HeldItem>().and_then(|mut held_item| held_item.0.take()) else { return };
+
+        match power_up {
+            PowerUp::SpeedBoost => {
+                kart.insert(SpeedBoost::new(boost_time));
+            },
+            PowerUp::Shield => {
+                kart.insert(common::health::Shield);
+            },
+            PowerUp::Repair => {
+                if let Some(mut health) = kart.get_mut::<common::health::Health>() {
+                    health.add(repair_amount);
+                }
+            },
+            PowerUp::Credits => {
+                if let Some(mut points) = kart.get_mut::<points::Points>() {
+                    points.0 += credit_bundle;
+                }
+            },
+            PowerUp::FreeTower => {
+                kart.insert(FreeTower);
+            },
+        }
+    }
+}
+
+fn pick_up_items(
+    mut collision_event_reader: EventReader<Collision>,
+    mut item_boxes: Query<(&mut ItemBox, &mut Visibility)>,
+    mut karts: Query<(&mut HeldItem, Option<&race::placement::Place>)>,
+    mut global_rng: ResMut<GlobalRng>,
+    game_config: Res<config::GameConfig>,
+) {
+    let racers = karts.iter().len();
+    for Collision(contacts) in collision_event_reader.read() {
+        let (item_box_entity, kart_entity) = if item_boxes.contains(contacts.entity1) {
+            (contacts.entity1, contacts.entity2)
+        } else {
+            (contacts.entity2, contacts.entity1)
+        };
+
+        let (Ok((mut item_box, mut visibility)), Ok((mut held_item, place))) = (item_boxes.get_mut(item_box_entity), karts.get_mut(kart_entity)) else { continue };
+        if !item_box.respawn.finished() || held_item.0.is_some() {
+            continue;
+        }
+
+        let behind = place.map(|place| place.0.saturating_sub(1) as f32 / racers.saturating_sub(1).max(1) as f32).unwrap_or(0.5);
+        held_item.0 = Some(PowerUp::roll(behind, global_rng.f32()));
+        item_box.respawn = Timer::from_seconds(game_config.item_box_respawn_time, TimerMode::Once);
+        *visibility = Visibility::Hidden;
+    }
+}
+
+fn respawn_item_boxes(
+    mut item_boxes: Query<(&mut ItemBox, &mut Visibility)>,
+    time: Res<Time>,
+) {
+    for (mut item_box, mut visibility) in &mut item_boxes {
+        if item_box.respawn.tick(time.delta()).just_finished() {
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+fn spin_item_boxes(
+    mut item_boxes: Query<&mut Transform, With<ItemBox>>,
+    time: Res<Time>,
+) {
+    for mut transform in &mut item_boxes {
+        transform.rotate_y(SPIN_SPEED * time.delta_seconds());
+    }
+}
+
+/// Bots don't save anything for later
+fn use_bot_items(
+    mut kart_action_event_writer: EventWriter<controller::KartActionEvent>,
+    bots: Query<(Entity, &HeldItem), With<bot::Bot>>,
+) {
+    for (entity, held_item) in &bots {
+        if held_item.0.is_some() {
+            kart_action_event_writer.send(controller::KartActionEvent { entity, action: controller::KartAction::UseItem });
+        }
+    }
+}
+
+fn boost_karts(
+    mut commands: Commands,
+    mut karts: Query<(Entity, &mut SpeedBoost, &Transform, &mut LinearVelocity)>,
+    game_config: Res<config::GameConfig>,
+    time: Res<Time>,
+) {
+    for (entity, mut speed_boost, transform, mut linear_velocity) in &mut karts {
+        if speed_boost.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<SpeedBoost>();
+        } else {
+            let direction = transform.forward();
+            linear_velocity.x += direction.x * game_config.boost_acceleration * time.delta_seconds();
+            linear_velocity.z += direction.z * game_config.boost_acceleration * time.delta_seconds();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roll_walks_the_weights_in_order() {
+        // the leader's weights add up to 9.5
+        assert_eq!(PowerUp::roll(0., 0.), PowerUp::SpeedBoost);
+        assert_eq!(PowerUp::roll(0., 2. / 9.5), PowerUp::Shield);
+        assert_eq!(PowerUp::roll(0., 4. / 9.5), PowerUp::Repair);
+        assert_eq!(PowerUp::roll(0., 6. / 9.5), PowerUp::Credits);
+        assert_eq!(PowerUp::roll(0., 9.2 / 9.5), PowerUp::FreeTower);
+        assert_eq!(PowerUp::roll(0., 1.), PowerUp::FreeTower);
+    }
+
+    #[test]
+    fn roll_favors_boosts_for_karts_behind() {
+        let count = |behind: f32, power_up: PowerUp| {
+            (0..100).filter(|i| PowerUp::roll(behind, *i as f32 / 100.) == power_up).count()
+        };
+
+        assert!(count(1., PowerUp::SpeedBoost) > count(0., PowerUp::SpeedBoost));
+        assert!(count(1., PowerUp::FreeTower) > count(0., PowerUp::FreeTower));
+        assert!(count(1., PowerUp::Credits) < count(0., PowerUp::Credits));
+    }
+}
@@ -6,11 +6,12 @@ use bevy_turborand::prelude::*;
 use std::f32::consts::TAU;
 use bevy_xpbd_3d::{math::*, prelude::*};
 use bevy_mod_outline::{OutlineBundle, OutlineVolume, OutlineMode};
-use crate::{util::audio, assets, util, AppState, IngameState};
-use super::{bot, controller, player, config, race, points, game_settings, particle, common, CleanupMarker, bullet, collisions};
+use crate::{util::audio, assets, ui, util, AppState, IngameState};
+use super::{bot, controller, player, config, race, points, game_settings, particle, common, CleanupMarker, bullet, collisions, tower, items};
 use bevy_xpbd_3d::PhysicsSet;
 use bevy::transform::TransformSystem;
 use bevy_kira_audio::prelude::*;
+use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "gizmos")]
 use bevy::gizmos::gizmos::Gizmos;
@@ -48,6 +49,45 @@ fn handle_kart_sounds(
 #[derive(Component)]
 pub struct Kart(pub Color, Handle<AudioInstance>);
 
+/// Shown above the kart and in the standings
+#[derive(Component, Clone)]
+pub struct KartName(pub String);
+
+/// Bots are named from this list, it's longer than the number of karts so names don't repeat
+const BOT_NAMES: [&str; 12] = [
+    "Axle", "Blitz", "Cog", "Dash", "Gizmo", "Nitro",
+    "Piston", "Rivet", "Sprocket", "Turbo", "Widget", "Zoom",
+];
+
+/// Picked before the race by the players and at random for bots
+#[derive(Component, Clone, Copy, PartialEq, Default, Debug, Serialize, Deserialize)]
+pub enum KartClass {
+    Light,
+    #[default]
+    Balanced,
+    Heavy,
+}
+
+impl KartClass {
+    pub const ALL: [KartClass; 3] = [KartClass::Light, KartClass::Balanced, KartClass::Heavy];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            KartClass::Light => "Light",
+            KartClass::Balanced => "Balanced",
+            KartClass::Heavy => "Heavy",
+        }
+    }
+
+    pub fn stats<'a>(&self, game_config: &'a config::GameConfig) -> &'a config::KartStats {
+        match self {
+            KartClass::Light => &game_config.light_kart,
+            KartClass::Balanced => &game_config.balanced_kart,
+            KartClass::Heavy => &game_config.heavy_kart,
+        }
+    }
+}
+
 #[derive(Event)]
 pub struct HitEvent {
     pub entity: Entity,
@@ -69,54 +109,73 @@ impl Default for Smoker {
 
 fn handle_deaths(
     mut commands: Commands,
-    karts: Query<(Entity, &Transform, &common::health::Health, &Kart, &KartColor, Has<player::Player>), >,
+    karts: Query<(Entity, &Transform, &common::health::Health, &Kart, &KartColor, &KartClass, &KartName, Option<&player::Player>, Has<race::Finished>), >,
+    mut kart_eliminated_event_writer: EventWriter<race::events::KartEliminated>,
+    mut race_finished_event_writer: EventWriter<race::events::RaceFinished>,
     mut bullet_hit_event_writer: EventWriter<bullet::CreateHitEvent>,
     time: Res<Time>,
     mut game_state: ResMut<game_settings::GameState>,
     game_assets: Res<assets::GameAssets>,
-    mut next_ingame_state: ResMut<NextState<IngameState>>,
+    game_config: Res<config::GameConfig>,
     mut current_state: ResMut<State<IngameState>>,
     mut game_audio: audio::GameAudio,
     audio: Res<Audio>,
 ) {
-    let mut player_is_dead = false;
+    let mut players_alive = 0;
     let mut player_exists= false;
-    for (entity, transform, health, kart, kart_color, is_player) in &karts {
+    for (entity, transform, health, kart, kart_color, class, name, player, is_finished) in &karts {
+        let is_player = player.is_some();
         if health.is_dead() {
             bullet_hit_event_writer.send(bullet::CreateHitEvent {
                 position: transform.translation,
-                count: config::KART_DIE_HIT_COUNT,
+                count: game_config.kart_die_hit_count,
                 material: game_assets.kart_colors[&kart_color.0].clone_weak(),
                 color: kart.0,
             });
             commands.entity(entity).despawn_recursive();
+            // karts that already crossed the line keep their result
+            if !is_finished {
+                kart_eliminated_event_writer.send(race::events::KartEliminated {
+                    entity,
+                    color: kart.0,
+                    player: player.map(|player| player.0),
+                    name: name.0.clone(),
+                    class: *class,
+                });
+            }
         }
 
         if is_player {
-            player_is_dead = health.is_dead(); 
             player_exists = true;
+            if health.is_alive() {
+                players_alive += 1;
+            }
         }
     }
     
     #[cfg(not(feature = "no_bots"))]
     {
         if *current_state.get() == IngameState::InGame{
-            let player_won = player_exists && karts.iter().len() <= 1;
-            let game_is_over = ((!player_exists || player_is_dead) || player_won);
+            let player_won = players_alive > 0 && karts.iter().len() <= 1 && !game_state.time_trial;
+            let game_is_over = if game_state.number_of_players == 0 {
+                // bot-only races run until a single kart is left
+                karts.iter().len() <= 1
+            } else {
+                (!player_exists || players_alive == 0) || player_won
+            };
             if game_is_over  {
                 game_audio.stop_bgm();
                 audio.stop();
             }
 
             if game_is_over && game_state.player_death_cooldown.tick(time.delta()).finished() {
-                if player_won {
-                    game_state.ending_state = game_settings::GameEndingState::Winner;
-                } else {
-                    game_state.ending_state = game_settings::GameEndingState::Died;
-                }
-                game_audio.stop_bgm();
-                audio.stop();
-                next_ingame_state.set(IngameState::EndGame);
+                race_finished_event_writer.send(race::events::RaceFinished {
+                    ending: if player_won {
+                        game_settings::GameEndingState::Winner
+                    } else {
+                        game_settings::GameEndingState::Died
+                    },
+                });
             }
         }
     }
@@ -160,16 +219,25 @@ fn upright_karts(
     }
 }
 
+/// Smoke color for each mini-turbo tier while drifting
+const DRIFT_SMOKE_COLORS: [Color; controller::MAX_DRIFT_TIER + 1] = [
+    Color::WHITE,
+    Color::rgb(0.3, 0.6, 1.0),
+    Color::ORANGE,
+    Color::PURPLE,
+];
+
 fn spawn_smoke(
     mut smoke_event_writer: EventWriter<particle::CreateParticleEvent>,
-    mut smokers: Query<(&mut Smoker, &Transform, &LinearVelocity, Has<controller::Braking>), With<controller::Grounded>>, 
+    mut smokers: Query<(&mut Smoker, &Transform, &LinearVelocity, Has<controller::Braking>, Option<&controller::Drifting>), With<controller::Grounded>>, 
     time: Res<Time>,
 ) {
-    for (mut smoker, transform, linear_velocity, is_braking) in &mut smokers {
+    for (mut smoker, transform, linear_velocity, is_braking, drifting) in &mut smokers {
         if smoker.cooldown.tick(time.delta()).just_finished() {
             if linear_velocity.0.length() > 3. && (is_braking || linear_velocity.0.angle_between(transform.forward()) > 0.4) {
                 smoke_event_writer.send(particle::CreateParticleEvent {
-                    position: *transform
+                    position: *transform,
+                    color: drifting.map(|drifting| DRIFT_SMOKE_COLORS[drifting.tier]).unwrap_or(Color::WHITE),
                 });
             }
         }
@@ -227,17 +295,52 @@ impl<C: Component + Clone> Command for KartSpawner<C> {
             ResMut<GlobalRng>,
             ResMut<game_settings::GameState>,
             Res<Audio>,
+            Res<config::GameConfig>,
+            Res<util::settings::SavedSettings>,
+            Res<Gamepads>,
             Query<Entity, With<player::Player>>,
+            Query<&KartName>,
         )> = SystemState::new(world);
 
-        let (mut assets_handler, mut game_assets, assets_gltf, mut global_rng, mut game_state, audio ,players) = system_state.get_mut(world);
+        let (mut assets_handler, mut game_assets, assets_gltf, mut global_rng, mut game_state, audio, game_config, saved_settings, gamepads, players, names) = system_state.get_mut(world);
+        let game_config = game_config.clone();
+        let control_bindings = saved_settings.control_bindings.clone();
         let matrix = self.global_transform.compute_matrix();
         let spawn_point = matrix.transform_point3(self.aabb.center.into());
         let rand = global_rng.f32_normalized();
         let positive_rand = global_rng.f32();
 
         let count_of_spawned_players = players.iter().count();
-        let color = game_state.kart_colors.pop().expect("Ran out of colors for the karts");
+        let spawn_index = names.iter().count();
+        let gamepad = util::input::gamepad_for_player(&gamepads, count_of_spawned_players);
+        let number_of_players = game_state.number_of_players;
+        let is_bot = count_of_spawned_players >= number_of_players;
+        let class = if is_bot {
+            KartClass::ALL[global_rng.usize(0..KartClass::ALL.len())]
+        } else {
+            game_state.kart_class
+        };
+        let bot_rng = is_bot.then(|| RngComponent::from(&mut global_rng));
+        let stats = class.stats(&game_config).clone();
+        let name = if is_bot {
+            let used = names.iter().map(|name| name.0.as_str()).collect::<Vec<_>>();
+            let available = BOT_NAMES.iter().filter(|name| !used.contains(*name)).collect::<Vec<_>>();
+            available.get(global_rng.usize(0..available.len().max(1))).map_or("Bot".to_string(), |name| name.to_string())
+        } else if count_of_spawned_players == 0 {
+            game_state.player_name.clone()
+        } else {
+            format!("Player {}", count_of_spawned_players + 1)
+        };
+
+        // the first player drives the color they picked and everyone else takes what's left
+        let picked_color = game_settings::GameState::default().kart_colors.get(game_state.player_color).copied();
+        let color = match picked_color {
+            Some(picked_color) if !is_bot && count_of_spawned_players == 0 && game_state.kart_colors.contains(&picked_color) => {
+                game_state.kart_colors.retain(|color| *color != picked_color);
+                picked_color
+            },
+            _ => game_state.kart_colors.pop().expect("Ran out of colors for the karts"),
+        };
         let kart_material = assets_handler.materials.add(color.into());
         let kart_color = KartColor(game_assets.add_kart_color(kart_material));
         let color_material = game_assets.kart_colors[&kart_color.0].clone_weak();
@@ -246,11 +349,16 @@ impl<C: Component + Clone> Command for KartSpawner<C> {
 
         #[cfg(feature = "no_bots")]
         {
-            if count_of_spawned_players >= config::NUMBER_OF_PLAYERS {
+            if count_of_spawned_players >= game_state.number_of_players {
                 return;
             }
         }
 
+        // time trials are driven alone
+        if game_state.time_trial && count_of_spawned_players >= number_of_players {
+            return;
+        }
+
         let gltf = assets_gltf.get(&game_assets.car);
         if let Some(gltf) = gltf {
             let scene = gltf.scenes[0].clone();
@@ -259,7 +367,7 @@ impl<C: Component + Clone> Command for KartSpawner<C> {
                 .with_volume(0.)
                 .looped()
                 .handle();
-            let mut entity = world.spawn((Kart(color, car_sound.clone()), kart_color));
+            let mut entity = world.spawn((Kart(color, car_sound.clone()), kart_color, class, KartName(name.clone())));
             let kart_id = entity.id();
             entity.insert((
                 AudioEmitter {
@@ -302,14 +410,26 @@ impl<C: Component + Clone> Command for KartSpawner<C> {
                 },
                 race::NextWayPoint(race::WayPoints::Quarter),
                 race::LapCounter(1),
-                race::PlaceCounter(0),
+                race::PlaceCounter {
+                    spawn_index,
+                    ..default()
+                },
+                race::lap_check::WrongWay::default(),
+                items::HeldItem::default(),
                 points::Points(8),
                 Smoker::default(), 
                 self.cleanup_marker,
                 Restitution::new(0.0),
                 CollisionLayers::new([collisions::Layer::Kart], [collisions::Layer::Ground, collisions::Layer::Kart]),
                 //controller::CommonControllerBundle::new(Collider::capsule(0.3, 0.5), Vector::NEG_Y * 9.81 * 1.5)
-                controller::CommonControllerBundle::new(Collider::cuboid(1.5, 1.0, 1.5), Vector::NEG_Y * config::GRAVITY_FORCE),
+                controller::CommonControllerBundle::new(Collider::cuboid(1.5 * stats.size, 1.0, 1.5 * stats.size), Vector::NEG_Y * game_config.gravity_force)
+                    .with_movement(
+                        game_config.base_acceleration * stats.acceleration,
+                        game_config.base_deceleration,
+                        game_config.movement_damping,
+                        game_config.rotation_damping * stats.turning,
+                        PI * game_config.max_slope,
+                    ),
             )).with_children(|builder| {
 //              builder.spawn((PbrBundle {
 //                  mesh: cube_mesh,
@@ -319,18 +439,34 @@ impl<C: Component + Clone> Command for KartSpawner<C> {
             });
 
 
-            if count_of_spawned_players >= config::NUMBER_OF_PLAYERS {
-                entity.insert(bot::BotBundle::new(rand, positive_rand));
+            if let Some(bot_rng) = bot_rng {
+                entity.insert(bot::BotBundle::new(rand, positive_rand, bot_rng));
             } else {
-                entity.insert((player::Player, controller::CharacterControllerKeyboard, ));
+                let player_id = count_of_spawned_players;
+                entity.insert((
+                    player::Player(player_id),
+                    util::input::create_game_input_for_player(player_id, &control_bindings, gamepad, number_of_players == 1),
+                    tower::SelectedTower::default(),
+                    controller::Drifter,
+                ));
             }
 
             common::health::HealthBarSpawner::<CleanupMarker> {
-                health_points: config::KART_HEALTH,
+                health_points: stats.health,
                 parent: kart_id, 
                 cleanup_marker: CleanupMarker,
                 offset: Vec3::new(0., 2.0, 0.),
             }.apply(world);
+
+            // a lone player's own name would sit right in front of their camera
+            if is_bot || number_of_players > 1 {
+                ui::follow_text::SpawnFollowText {
+                    follow_entity: kart_id,
+                    text: name,
+                    color,
+                    cleanup_marker: CleanupMarker,
+                }.apply(world);
+            }
         }
     }
 }
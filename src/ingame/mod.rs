@@ -10,11 +10,11 @@ pub mod bot;
 mod bullet;
 pub mod camera; 
 mod common;
-mod kart;
+pub mod kart;
 mod controller;
 mod collisions;
 mod path;
-mod race;
+pub mod race;
 mod finish_line;
 pub mod game_settings;
 mod points;
@@ -23,18 +23,24 @@ mod ui;
 pub mod player;
 pub mod tower;
 pub mod config;
+pub mod track;
+pub mod replay;
+mod pause;
+pub mod items;
 
 pub struct InGamePlugin;
 impl Plugin for InGamePlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((camera::CameraPlugin, controller::CharacterControllerPlugin, tower::TowerPlugin, bullet::BulletPlugin, bot::BotPlugin, path::PathPlugin, finish_line::FinishLinePlugin, race::RacePlugin, collisions::CollisionsPlugin, ui::InGameUIPlugin, kart::KartPlugin, particle::ParticlePlugin, common::CommonPlugin,))
+        app.add_plugins((camera::CameraPlugin, controller::CharacterControllerPlugin, tower::TowerPlugin, bullet::BulletPlugin, bot::BotPlugin, path::PathPlugin, finish_line::FinishLinePlugin, race::RacePlugin, collisions::CollisionsPlugin, ui::InGameUIPlugin, kart::KartPlugin, particle::ParticlePlugin, common::CommonPlugin, config::ConfigPlugin, replay::ReplayPlugin,))
+            .add_plugins((pause::PausePlugin, items::ItemsPlugin))
             .init_resource::<game_settings::GameState>()
             .add_systems(Update, game_settings::update_game_state.run_if(in_state(IngameState::InGame)))
             .add_systems(OnExit(AppState::InGame), cleanup::<CleanupMarker>)
-            .add_systems(OnExit(IngameState::InGame), stop_audio)
+            // pausing keeps the race's sounds around to resume them
+            .add_systems(OnExit(IngameState::InGame), stop_audio.run_if(not(in_state(IngameState::Paused))))
             .add_systems(OnEnter(AppState::InGame), stop_audio)
             .add_systems(OnExit(AppState::Controls), stop_audio)
-            .add_systems(OnEnter(AppState::InGame), setup);
+            .add_systems(OnEnter(AppState::InGame), (config::apply_game_config, replay::start_race, setup).chain());
 
         if cfg!(feature = "colliders") {
             app.add_plugins(PhysicsDebugPlugin::default());
@@ -52,10 +58,13 @@ impl Command for IngameLoader {
         let mut system_state: SystemState<(
             assets::loader::AssetsHandler,
             ResMut<assets::GameAssets>,
+            Res<game_settings::GameState>,
         )> = SystemState::new(world);
-        let (mut assets_handler, mut game_assets) = system_state.get_mut(world);
+        let (mut assets_handler, mut game_assets, game_state) = system_state.get_mut(world);
+        let track = track::get(game_state.track);
 
-        assets_handler.add_glb(&mut game_assets.track, "models/track.glb");
+        assets_handler.add_game_config(&mut game_assets.game_config, "game.config.ron");
+        assets_handler.add_glb(&mut game_assets.track, track.path);
         assets_handler.add_glb(&mut game_assets.car, "models/tower_car.glb");
         assets_handler.add_animation(&mut game_assets.drive_animation,"models/tower_car.glb#Animation0");
         assets_handler.add_glb(&mut game_assets.tower_01, "models/tower.glb");
@@ -74,12 +83,15 @@ impl Command for IngameLoader {
         assets_handler.add_audio(&mut game_assets.sfx_lap, "audio/lap.wav");
         assets_handler.add_audio(&mut game_assets.sfx_shot, "audio/shot.wav");
         assets_handler.add_audio(&mut game_assets.sfx_tower, "audio/tower.wav");
-        assets_handler.add_audio(&mut game_assets.bgm_1, "audio/bgm.ogg");
+        assets_handler.add_audio(&mut game_assets.bgm_1, track.music);
         assets_handler.add_audio(&mut game_assets.bgm_2, "audio/end_bgm.ogg");
 
         assets_handler.add_standard_mesh(&mut game_assets.smoke, Mesh::from(shape::Plane { size: 0.5, subdivisions: 0 }));
         assets_handler.add_standard_mesh(&mut game_assets.hit_particle, shape::UVSphere { radius: 0.7, sectors: 3, stacks: 6 }.into());
         assets_handler.add_standard_mesh(&mut game_assets.bullet_mesh, shape::UVSphere { radius: 1.0, sectors: 3, stacks: 6 }.into());
+        assets_handler.add_standard_mesh(&mut game_assets.mine_mesh, shape::UVSphere { radius: 0.8, sectors: 6, stacks: 3 }.into());
+        assets_handler.add_standard_mesh(&mut game_assets.slow_field_mesh, shape::Cylinder { radius: 1.5, height: 0.4, ..default() }.into());
+        assets_handler.add_standard_mesh(&mut game_assets.barrier_mesh, shape::Box::new(2.5, 1.0, 0.5).into());
 
         assets_handler.add_mesh(
             &mut game_assets.cannon.mesh,
@@ -183,16 +195,21 @@ fn setup(
                             );
                         }
 
-                        if name.contains("place_sensor") {
-                            cmds.insert(
-                                (race::placement_sensor::PlaceSensor::new(name), 
-                                Visibility::Hidden,
-                                Collider::trimesh_from_mesh(mesh).unwrap(), 
+                        if name.contains("item_box") {
+                            cmds.insert((
+                                items::ItemBox::default(),
+                                Collider::trimesh_from_mesh(mesh).unwrap(),
+                                Sensor,
                             ));
                         }
+
+                        if name.contains("place_sensor") {
+                            cmds.insert(Visibility::Hidden);
+                        }
                     }
                 })
             }, 
+            util::scene_hook::SceneOnComplete::new(|cmds| cmds.add(path::PathBuilder)),
             CleanupMarker,
         ));
     }
@@ -267,7 +284,7 @@ fn setup(
         CleanupMarker,
     ));
 
-    commands.add(camera::SpawnCamera { cleanup_marker: CleanupMarker });
+    commands.add(camera::SpawnCamera { cleanup_marker: CleanupMarker, number_of_players: game_state.number_of_players });
 
     #[cfg(feature = "debug")]
     next_ingame_state.set(IngameState::InGame);
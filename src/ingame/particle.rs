@@ -1,4 +1,4 @@
-use crate::{assets, ingame};
+use crate::{assets, ingame, ingame::{camera, game_settings}};
 use bevy::prelude::*;
 
 pub struct ParticlePlugin;
@@ -12,6 +12,7 @@ impl Plugin for ParticlePlugin {
 #[derive(Event)]
 pub struct CreateParticleEvent {
     pub position: Transform,
+    pub color: Color,
 }
 
 #[derive(Component)]
@@ -21,15 +22,16 @@ pub struct Particle {
     position: Vec3,
 }
 
+/// Faces the camera of the player with the matching index
 #[derive(Component)]
-pub struct Billboard;
+pub struct Billboard(pub usize);
 
 fn handle_billboards(
-    mut billboards: Query<&mut Transform, With<Billboard>>,
-    camera: Query<&Transform, (With<Camera>, Without<Billboard>)>,
+    mut billboards: Query<(&mut Transform, &Billboard)>,
+    cameras: Query<(&Transform, &camera::PlayerCamera), Without<Billboard>>,
 ) {
-    if let Ok(camera) = camera.get_single() {
-        for mut billboard in billboards.iter_mut() {
+    for (mut billboard, Billboard(player)) in billboards.iter_mut() {
+        if let Some((camera, _)) = cameras.iter().find(|(_, c)| c.0 == *player) {
             billboard.look_at(camera.translation, Vec3::Y);
         }
     }
@@ -78,40 +80,45 @@ pub fn handle_create_particle_event(
     mut create_particle_event_reader: EventReader<CreateParticleEvent>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     game_assets: Res<assets::GameAssets>,
+    game_state: Res<game_settings::GameState>,
 ) {
     for event in create_particle_event_reader.read() {
-        let material = materials.add(StandardMaterial {
-                           base_color_texture: Some(game_assets.smoke_image.image.clone()),
-                           alpha_mode: AlphaMode::Blend,
-                           ..Default::default()
-                       });
-
-
         let base_location = event.position.translation + event.position.back().normalize() * 1.2;
         let left = base_location + event.position.left().normalize() * 1.2;
         let right = base_location + event.position.right().normalize() * 1.2;
 
-        for translation in [left, right].iter() {
-            let transform = Transform::from_translation(*translation);
-            let mut billboard= commands .spawn( ( SpatialBundle::from_transform(transform), Billboard),);
-            let billboard_id = billboard.id();
-            billboard.with_children(|parent| {
-                    parent.spawn((PbrBundle {
-                        mesh: game_assets.smoke.clone(),
-                        material: material.clone(),
-                        transform: Transform::from_rotation(
-                            Quat::from_axis_angle(Vec3::X, (3.0 * std::f32::consts::PI) / 2.0)),
-                        ..Default::default()
-                    },
-                    bevy::pbr::NotShadowCaster,
-                    Particle {
-                        parent: billboard_id,
-                        time_to_live: Timer::from_seconds(2., TimerMode::Once),
-                        position: transform.translation,
-                    },
-                    ingame::CleanupMarker,
-                    ));
-                });
+        // every camera gets its own copy so the smoke faces each viewport
+        for player in 0..game_state.number_of_players.max(1) {
+            let material = materials.add(StandardMaterial {
+                               base_color: event.color,
+                               base_color_texture: Some(game_assets.smoke_image.image.clone()),
+                               alpha_mode: AlphaMode::Blend,
+                               ..Default::default()
+                           });
+
+            for translation in [left, right].iter() {
+                let transform = Transform::from_translation(*translation);
+                let mut billboard= commands .spawn( ( SpatialBundle::from_transform(transform), Billboard(player)),);
+                let billboard_id = billboard.id();
+                billboard.with_children(|parent| {
+                        parent.spawn((PbrBundle {
+                            mesh: game_assets.smoke.clone(),
+                            material: material.clone(),
+                            transform: Transform::from_rotation(
+                                Quat::from_axis_angle(Vec3::X, (3.0 * std::f32::consts::PI) / 2.0)),
+                            ..Default::default()
+                        },
+                        bevy::pbr::NotShadowCaster,
+                        camera::player_layer(player),
+                        Particle {
+                            parent: billboard_id,
+                            time_to_live: Timer::from_seconds(2., TimerMode::Once),
+                            position: transform.translation,
+                        },
+                        ingame::CleanupMarker,
+                        ));
+                    });
+            }
         }
     }
 }
@@ -3,6 +3,10 @@ use crate::{AppState, };
 use super::controller;
 use std::collections::HashMap;
 
+mod spline;
+
+use self::spline::TrackSpline;
+
 pub struct PathPlugin;
 impl Plugin for PathPlugin {
     fn build(&self, app: &mut App) {
@@ -45,42 +49,48 @@ impl Command for PathAdder {
                 // there should only be one of these
                 path_manager.points.insert(0, point);
             }
-
-            path_manager.build(); // TODO: find a better place to do this
         }
     }
 }
 
+/// Lays the spline through the path points, added from the track's scene hook once
+/// it's gone through the whole scene so every `PathAdder` has already run
+pub struct PathBuilder;
+impl Command for PathBuilder {
+    fn apply(self, world: &mut World) {
+        world.resource_mut::<PathManager>().build();
+    }
+}
+
+/// The racing line through the track. Path points are named in order in the
+/// track's gltf, the line between them is smoothed out by a [`TrackSpline`] so
+/// anything along the track can also be found by its distance from the first point.
 #[derive(Default, Resource)]
 pub struct PathManager {
     points: HashMap<usize, Vec3>,
     path: Vec<Vec3>,
+    keys: Vec<usize>,
+    spline: TrackSpline,
 }
 
 impl PathManager {
     pub fn clear(&mut self) {
         self.points = HashMap::default();
         self.path = Vec::default();
+        self.keys = Vec::default();
+        self.spline = TrackSpline::default();
     }
 
     pub fn build(&mut self) {
         let mut points = self.points.iter().collect::<Vec::<_>>(); 
         points.sort_by_key(|x| x.0);
+        self.keys = points.iter().map(|x| *x.0).collect();
         self.path = points.into_iter().map(|x| *x.1).collect();
+        self.spline = TrackSpline::new(&self.path);
     }
 
     pub fn get_closest_index(&self, point: Vec3) -> Option<usize> {
-        let mut closest_index = None;
-        let mut closest= f32::MAX;
-        for (key, val) in self.points.iter() {
-            let distance = point.distance(*val);
-            if distance < closest {
-                closest = distance;
-                closest_index = Some(*key);
-            }
-        }
-
-        closest_index
+        self.spline.closest_point(point).map(|i| self.keys[i])
     }
 
     pub fn get_next(&self, index: usize) -> Option<usize> {
@@ -104,11 +114,60 @@ impl PathManager {
         }
     }
 
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// True until there are enough path points to lay a spline through
+    pub fn is_empty(&self) -> bool {
+        self.spline.is_empty()
+    }
+
     /// Assumes index is valid and will panic otherwise
     pub fn get(&self, index: usize) -> Vec3 {
         self.points[&index]
     }
 
+    /// Length of one lap along the spline
+    pub fn length(&self) -> f32 {
+        self.spline.length()
+    }
+
+    /// Average distance between two path points along the spline
+    pub fn point_spacing(&self) -> f32 {
+        self.spline.length() / self.path.len().max(1) as f32
+    }
+
+    /// Distance along the track from the first path point to the closest spot on the spline
+    pub fn progress_at(&self, point: Vec3) -> f32 {
+        self.spline.progress_at(point)
+    }
+
+    /// How far `to` is ahead of `from`, wrapping around the lap
+    pub fn distance_between(&self, from: f32, to: f32) -> f32 {
+        (to - from).rem_euclid(self.spline.length().max(f32::EPSILON))
+    }
+
+    pub fn point_at(&self, distance: f32) -> Vec3 {
+        self.spline.point_at(distance)
+    }
+
+    pub fn tangent_at(&self, distance: f32) -> Vec3 {
+        self.spline.tangent_at(distance)
+    }
+
+    /// Radians the track turns left or right per unit of distance
+    pub fn curvature_at(&self, distance: f32) -> f32 {
+        self.spline.curvature_at(distance)
+    }
+
+    /// Total angle the track turns through over the next `length` units from `distance`
+    pub fn turn_over(&self, distance: f32, length: f32) -> f32 {
+        let steps = (length * 4. / self.point_spacing().max(1.)).ceil().max(1.) as usize;
+        let step = length / steps as f32;
+        (0..steps).map(|i| self.curvature_at(distance + step * (i as f32 + 0.5)) * step).sum()
+    }
+
 }
 
 #[cfg(feature = "gizmos")]
@@ -0,0 +1,293 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+/// Points sampled along each span between two path points
+const SAMPLES_PER_SPAN: usize = 8;
+/// Size of the grid cells samples are bucketed into on the XZ plane
+const CELL_SIZE: f32 = 20.0;
+
+struct Sample {
+    position: Vec3,
+    /// Arc length from the first path point
+    distance: f32,
+}
+
+/// Closed Catmull-Rom spline through the path points, sampled by arc length so
+/// positions along the track can be given as a distance from the first point.
+#[derive(Default)]
+pub struct TrackSpline {
+    samples: Vec<Sample>,
+    length: f32,
+    grid: HashMap<(i32, i32), Vec<usize>>,
+    min_cell: (i32, i32),
+    max_cell: (i32, i32),
+}
+
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * ((2. * p1)
+        + (p2 - p0) * t
+        + (2. * p0 - 5. * p1 + 4. * p2 - p3) * t2
+        + (3. * p1 - p0 - 3. * p2 + p3) * t3)
+}
+
+fn cell(point: Vec3) -> (i32, i32) {
+    ((point.x / CELL_SIZE).floor() as i32, (point.z / CELL_SIZE).floor() as i32)
+}
+
+impl TrackSpline {
+    pub fn new(points: &[Vec3]) -> Self {
+        let mut spline = TrackSpline::default();
+        if points.len() < 2 {
+            return spline;
+        }
+
+        let count = points.len();
+        let mut distance = 0.;
+        for span in 0..count {
+            let p0 = points[(span + count - 1) % count];
+            let p1 = points[span];
+            let p2 = points[(span + 1) % count];
+            let p3 = points[(span + 2) % count];
+
+            for step in 0..SAMPLES_PER_SPAN {
+                let position = catmull_rom(p0, p1, p2, p3, step as f32 / SAMPLES_PER_SPAN as f32);
+                if let Some(previous) = spline.samples.last() {
+                    distance += previous.position.distance(position);
+                }
+                spline.samples.push(Sample { position, distance });
+            }
+        }
+        spline.length = distance + spline.samples[spline.samples.len() - 1].position.distance(spline.samples[0].position);
+
+        spline.min_cell = (i32::MAX, i32::MAX);
+        spline.max_cell = (i32::MIN, i32::MIN);
+        for (index, sample) in spline.samples.iter().enumerate() {
+            let (x, z) = cell(sample.position);
+            spline.min_cell = (spline.min_cell.0.min(x), spline.min_cell.1.min(z));
+            spline.max_cell = (spline.max_cell.0.max(x), spline.max_cell.1.max(z));
+            spline.grid.entry((x, z)).or_default().push(index);
+        }
+
+        spline
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn length(&self) -> f32 {
+        self.length
+    }
+
+    /// Sample after `index`, with the distance to it carried past the end of the lap
+    fn next_sample(&self, index: usize) -> (Vec3, f32) {
+        if index + 1 < self.samples.len() {
+            let next = &self.samples[index + 1];
+            (next.position, next.distance)
+        } else {
+            (self.samples[0].position, self.length)
+        }
+    }
+
+    /// Searches the grid ring by ring around the point until nothing closer can be found
+    fn closest_sample(&self, point: Vec3) -> Option<usize> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let (x, z) = cell(point);
+        let max_ring = [x - self.min_cell.0, self.max_cell.0 - x, z - self.min_cell.1, self.max_cell.1 - z]
+            .into_iter()
+            .max()?
+            .max(0);
+
+        let mut closest: Option<(usize, f32)> = None;
+        for ring in 0..=max_ring {
+            let cells = (-ring..=ring)
+                .flat_map(|dx| [(x + dx, z - ring), (x + dx, z + ring)])
+                .chain((-ring + 1..ring).flat_map(|dz| [(x - ring, z + dz), (x + ring, z + dz)]));
+
+            for index in cells.filter_map(|cell| self.grid.get(&cell)).flatten() {
+                let distance = self.samples[*index].position.distance_squared(point);
+                if closest.map_or(true, |(_, closest_distance)| distance < closest_distance) {
+                    closest = Some((*index, distance));
+                }
+            }
+
+            // every cell in the next ring is at least this far away
+            if let Some((_, closest_distance)) = closest {
+                let reach = ring as f32 * CELL_SIZE;
+                if reach * reach >= closest_distance {
+                    break;
+                }
+            }
+        }
+
+        closest.map(|(index, _)| index)
+    }
+
+    /// Position in the list of path points of the one closest along the spline
+    pub fn closest_point(&self, point: Vec3) -> Option<usize> {
+        let index = self.closest_sample(point)?;
+        let count = self.samples.len() / SAMPLES_PER_SPAN;
+        Some(((index + SAMPLES_PER_SPAN / 2) / SAMPLES_PER_SPAN) % count)
+    }
+
+    /// Distance along the track of the closest point on the spline
+    pub fn progress_at(&self, point: Vec3) -> f32 {
+        let Some(index) = self.closest_sample(point) else { return 0. };
+        let previous = (index + self.samples.len() - 1) % self.samples.len();
+
+        [previous, index]
+            .into_iter()
+            .map(|start| {
+                let from = &self.samples[start];
+                let (to, to_distance) = self.next_sample(start);
+                let span = to - from.position;
+                let t = ((point - from.position).dot(span) / span.length_squared().max(f32::EPSILON)).clamp(0., 1.);
+                let projected = from.position + span * t;
+                (projected.distance_squared(point), from.distance + (to_distance - from.distance) * t)
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, progress)| progress.rem_euclid(self.length.max(f32::EPSILON)))
+            .unwrap_or(0.)
+    }
+
+    /// Point on the spline at a distance along the track, wrapping around laps
+    pub fn point_at(&self, distance: f32) -> Vec3 {
+        if self.is_empty() {
+            return Vec3::ZERO;
+        }
+
+        let distance = distance.rem_euclid(self.length.max(f32::EPSILON));
+        let index = self.samples.partition_point(|sample| sample.distance <= distance).saturating_sub(1);
+        let from = &self.samples[index];
+        let (to, to_distance) = self.next_sample(index);
+        let t = (distance - from.distance) / (to_distance - from.distance).max(f32::EPSILON);
+
+        from.position.lerp(to, t)
+    }
+
+    /// Direction the track heads at a distance along it
+    pub fn tangent_at(&self, distance: f32) -> Vec3 {
+        let step = self.sample_spacing();
+        (self.point_at(distance + step) - self.point_at(distance - step)).normalize_or_zero()
+    }
+
+    /// How sharply the track turns left or right at a distance along it, in radians per unit of distance
+    pub fn curvature_at(&self, distance: f32) -> f32 {
+        let step = self.sample_spacing();
+        let flatten = |tangent: Vec3| (tangent * Vec3::new(1., 0., 1.)).normalize_or_zero();
+        let (before, after) = (flatten(self.tangent_at(distance - step)), flatten(self.tangent_at(distance + step)));
+        if before == Vec3::ZERO || after == Vec3::ZERO {
+            return 0.;
+        }
+
+        let angle = before.angle_between(after);
+        if angle.is_nan() { 0. } else { angle / (2. * step) }
+    }
+
+    fn sample_spacing(&self) -> f32 {
+        self.length / self.samples.len().max(1) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    const RADIUS: f32 = 100.;
+    const POINTS: usize = 16;
+
+    fn circle() -> TrackSpline {
+        let points = (0..POINTS)
+            .map(|i| {
+                let angle = i as f32 / POINTS as f32 * TAU;
+                Vec3::new(angle.cos() * RADIUS, 0., angle.sin() * RADIUS)
+            })
+            .collect::<Vec<_>>();
+        TrackSpline::new(&points)
+    }
+
+    fn brute_force_closest(spline: &TrackSpline, point: Vec3) -> usize {
+        (0..spline.samples.len())
+            .min_by(|a, b| {
+                spline.samples[*a].position.distance_squared(point).total_cmp(&spline.samples[*b].position.distance_squared(point))
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn needs_two_points() {
+        assert!(TrackSpline::new(&[]).is_empty());
+        assert!(TrackSpline::new(&[Vec3::ZERO]).is_empty());
+        assert_eq!(TrackSpline::new(&[Vec3::ZERO]).progress_at(Vec3::ONE), 0.);
+    }
+
+    #[test]
+    fn arc_length_matches_the_loop() {
+        let spline = circle();
+        assert_eq!(spline.samples.len(), POINTS * SAMPLES_PER_SPAN);
+        assert!((spline.length() - TAU * RADIUS).abs() < TAU * RADIUS * 0.01);
+        assert!(spline.samples.windows(2).all(|pair| pair[0].distance < pair[1].distance));
+        assert!(spline.samples.last().unwrap().distance < spline.length());
+    }
+
+    #[test]
+    fn ring_search_finds_the_closest_sample() {
+        let spline = circle();
+        let points = [
+            Vec3::ZERO,
+            Vec3::new(RADIUS, 0., 0.),
+            Vec3::new(-RADIUS * 0.7, 3., RADIUS * 0.7),
+            Vec3::new(RADIUS * 5., 0., -RADIUS * 3.),
+            Vec3::new(13., 0., -97.),
+        ];
+        for point in points {
+            let found = spline.closest_sample(point).unwrap();
+            let expected = brute_force_closest(&spline, point);
+            assert_eq!(
+                spline.samples[found].position.distance_squared(point),
+                spline.samples[expected].position.distance_squared(point)
+            );
+        }
+    }
+
+    #[test]
+    fn closest_point_is_the_path_index() {
+        let spline = circle();
+        assert_eq!(spline.closest_point(Vec3::new(RADIUS, 0., 0.)), Some(0));
+        let angle = 5. / POINTS as f32 * TAU;
+        assert_eq!(spline.closest_point(Vec3::new(angle.cos(), 0., angle.sin()) * RADIUS * 1.1), Some(5));
+    }
+
+    #[test]
+    fn progress_round_trips_through_point_at() {
+        let spline = circle();
+        for distance in [1., 10., spline.length() * 0.25, spline.length() * 0.5, spline.length() - 10.] {
+            let progress = spline.progress_at(spline.point_at(distance));
+            assert!((progress - distance).abs() < 0.5, "{} came back as {}", distance, progress);
+        }
+    }
+
+    #[test]
+    fn progress_wraps_around_the_start() {
+        let spline = circle();
+        let just_after = spline.progress_at(spline.point_at(2.));
+        let just_before = spline.progress_at(spline.point_at(-2.));
+        assert!(just_after < 3.);
+        assert!(just_before > spline.length() - 3.);
+        assert!(just_before < spline.length());
+    }
+
+    #[test]
+    fn point_at_wraps_around_laps() {
+        let spline = circle();
+        let distance = spline.length() * 0.3;
+        assert!(spline.point_at(distance).distance(spline.point_at(distance + spline.length())) < 0.01);
+        assert!(spline.point_at(-distance).distance(spline.point_at(spline.length() - distance)) < 0.01);
+    }
+}
@@ -0,0 +1,352 @@
+use bevy::prelude::*;
+use bevy_kira_audio::prelude::*;
+use leafwing_input_manager::prelude::*;
+use crate::assets::command_ext::*;
+use crate::{assets, cleanup, menu::{settings::{Settings, SettingsMenuState}, MenuOption}, ui, util::{audio, input, settings}, AppState, IngameState};
+use super::{game_settings, replay};
+
+pub struct PausePlugin;
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PauseMenuState>()
+            .add_systems(
+                Update,
+                open_pause_menu.run_if(in_state(IngameState::InGame).and_then(replay::is_recording)),
+            )
+            .add_systems(OnEnter(IngameState::Paused), (freeze, setup))
+            .add_systems(
+                Update,
+                (handle_input, show_page, highlight_selection, update_setting_values)
+                    .chain()
+                    .run_if(in_state(AppState::InGame).and_then(in_state(IngameState::Paused))),
+            )
+            .add_systems(OnExit(IngameState::Paused), (unfreeze, cleanup::<CleanupMarker>));
+    }
+}
+
+#[derive(Component)]
+struct CleanupMarker;
+/// Holds the pause options, hidden while the settings are open
+#[derive(Component)]
+struct OptionsPage;
+#[derive(Component)]
+struct SettingsPage;
+#[derive(Component)]
+struct SettingValue(Settings);
+
+/// What can be changed mid race, the rest of the settings only matter when a race starts.
+/// `Go` closes the settings.
+const PAUSE_SETTINGS: [Settings; 5] = [
+    Settings::MasterVolume,
+    Settings::MusicVolume,
+    Settings::SfxVolume,
+    Settings::EngineVolume,
+    Settings::Go,
+];
+
+#[derive(Default, Resource)]
+struct PauseMenuState {
+    selected: PauseOption,
+    /// Set while the settings are open over the pause menu
+    settings: Option<SettingsMenuState>,
+}
+
+#[derive(Component, Copy, Clone, PartialEq, Default)]
+enum PauseOption {
+    #[default]
+    Resume,
+    Restart,
+    Settings,
+    Quit,
+}
+
+impl MenuOption<4> for PauseOption {
+    const ITEM: [PauseOption; 4] = [
+        PauseOption::Resume,
+        PauseOption::Restart,
+        PauseOption::Settings,
+        PauseOption::Quit,
+    ];
+
+    fn get_label(&self) -> &str {
+        match self {
+            PauseOption::Resume => "Resume",
+            PauseOption::Restart => "Restart Race",
+            PauseOption::Settings => "Settings",
+            PauseOption::Quit => "Quit to Title",
+        }
+    }
+}
+
+fn open_pause_menu(
+    players: Query<&ActionState<input::GameAction>>,
+    mut next_ingame_state: ResMut<NextState<IngameState>>,
+) {
+    if players.iter().any(|action_state| action_state.just_pressed(input::GameAction::Pause)) {
+        next_ingame_state.set(IngameState::Paused);
+    }
+}
+
+/// Stops the clock the race runs on along with anything playing. Physics, tower
+/// cooldowns and the race time all step off virtual time so they stop with it,
+/// and the paused frames are recorded with no time passing so replays still line up.
+fn freeze(
+    mut time: ResMut<Time<Virtual>>,
+    audio: Res<Audio>,
+) {
+    time.pause();
+    audio.pause();
+}
+
+fn unfreeze(
+    mut time: ResMut<Time<Virtual>>,
+    audio: Res<Audio>,
+    current_state: Res<State<IngameState>>,
+) {
+    time.unpause();
+    // the engines are still looping so they're stopped when leaving the race
+    if *current_state.get() == IngameState::InGame {
+        audio.resume();
+    } else {
+        audio.stop();
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut pause_menu_state: ResMut<PauseMenuState>,
+    game_assets: Res<assets::GameAssets>,
+    game_state: Res<game_settings::GameState>,
+    text_scaler: ui::text_size::TextScaler,
+) {
+    *pause_menu_state = PauseMenuState::default();
+    let text_style = TextStyle {
+        font: game_assets.font.clone(),
+        font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE * 0.8),
+        color: Color::WHITE,
+    };
+
+    for player in 0..game_state.number_of_players.max(1) {
+        commands.spawn((input::create_menu_input_for_player(player), CleanupMarker));
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.5).into(),
+                z_index: ZIndex::Global(100),
+                ..default()
+            },
+            CleanupMarker,
+        ))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    "Paused",
+                    TextStyle {
+                        font: game_assets.font.clone(),
+                        font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE * 1.2),
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Percent(2.)),
+                    ..default()
+                }),
+            );
+
+            parent
+                .spawn((page_bundle(Display::Flex), OptionsPage))
+                .with_children(|parent| {
+                    for option in PauseOption::get() {
+                        parent
+                            .spawn((row_bundle(JustifyContent::Center), option))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(option.get_label(), text_style.clone()));
+                            });
+                    }
+                });
+
+            parent
+                .spawn((page_bundle(Display::None), SettingsPage))
+                .with_children(|parent| {
+                    for setting in PAUSE_SETTINGS {
+                        parent
+                            .spawn((row_bundle(JustifyContent::SpaceBetween), setting))
+                            .with_children(|parent| {
+                                let label = if setting == Settings::Go { "Back" } else { setting.get_label() };
+                                parent.spawn(TextBundle::from_section(label, text_style.clone()));
+                                if setting != Settings::Go {
+                                    parent.spawn((TextBundle::from_section("", text_style.clone()), SettingValue(setting)));
+                                }
+                            });
+                    }
+                });
+        });
+}
+
+fn page_bundle(display: Display) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            width: Val::Percent(100.),
+            display,
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        ..default()
+    }
+}
+
+fn row_bundle(justify_content: JustifyContent) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            width: Val::Percent(30.),
+            padding: UiRect::all(Val::Percent(1.)),
+            justify_content,
+            ..default()
+        },
+        ..default()
+    }
+}
+
+fn handle_input(
+    mut commands: Commands,
+    mut pause_menu_state: ResMut<PauseMenuState>,
+    menu_inputs: Query<&ActionState<input::MenuAction>>,
+    players: Query<&ActionState<input::GameAction>>,
+    mut next_ingame_state: ResMut<NextState<IngameState>>,
+    mut game_state: ResMut<game_settings::GameState>,
+    game_assets: Res<assets::GameAssets>,
+    saved_settings: Res<settings::SavedSettings>,
+    mut audio: audio::GameAudio,
+) {
+    let just_pressed = |action| menu_inputs.iter().any(|action_state| action_state.just_pressed(action));
+
+    if let Some(settings_state) = pause_menu_state.settings.as_mut() {
+        if just_pressed(input::MenuAction::Up) {
+            audio.play_sfx(&game_assets.sfx_1);
+            settings_state.selected_setting = step_setting(settings_state.selected_setting, PAUSE_SETTINGS.len() - 1);
+        }
+
+        if just_pressed(input::MenuAction::Down) {
+            audio.play_sfx(&game_assets.sfx_1);
+            settings_state.selected_setting = step_setting(settings_state.selected_setting, 1);
+        }
+
+        // volumes are heard straight away like on the settings screen
+        if just_pressed(input::MenuAction::Left) {
+            audio.play_sfx(&game_assets.sfx_1);
+            settings_state.decrement();
+            commands.insert_resource(settings_state.apply_to(&saved_settings));
+        }
+
+        if just_pressed(input::MenuAction::Right) {
+            audio.play_sfx(&game_assets.sfx_1);
+            settings_state.increment();
+            commands.insert_resource(settings_state.apply_to(&saved_settings));
+        }
+
+        let back = just_pressed(input::MenuAction::Back)
+            || (just_pressed(input::MenuAction::Select) && settings_state.selected_setting == Settings::Go);
+        if back {
+            audio.play_sfx(&game_assets.sfx_1);
+            saved_settings.save();
+            pause_menu_state.settings = None;
+        }
+        return;
+    }
+
+    if just_pressed(input::MenuAction::Up) {
+        audio.play_sfx(&game_assets.sfx_1);
+        pause_menu_state.selected = pause_menu_state.selected.previous();
+    }
+
+    if just_pressed(input::MenuAction::Down) {
+        audio.play_sfx(&game_assets.sfx_1);
+        pause_menu_state.selected = pause_menu_state.selected.next();
+    }
+
+    // pausing again is the quickest way back
+    if players.iter().any(|action_state| action_state.just_pressed(input::GameAction::Pause)) {
+        next_ingame_state.set(IngameState::InGame);
+        return;
+    }
+
+    if just_pressed(input::MenuAction::Select) {
+        match pause_menu_state.selected {
+            PauseOption::Resume => {
+                next_ingame_state.set(IngameState::InGame);
+            },
+            PauseOption::Restart => {
+                *game_state = game_state.for_restart();
+                next_ingame_state.set(IngameState::Disabled);
+                commands.load_state(AppState::InGame);
+            },
+            PauseOption::Settings => {
+                let mut settings_state = SettingsMenuState::default();
+                settings_state.load(&saved_settings);
+                settings_state.selected_setting = PAUSE_SETTINGS[0];
+                pause_menu_state.settings = Some(settings_state);
+            },
+            PauseOption::Quit => {
+                next_ingame_state.set(IngameState::Disabled);
+                commands.load_state(AppState::TitleScreen);
+            },
+        }
+    }
+}
+
+/// The setting `step` places further down the pause settings, wrapping around
+fn step_setting(setting: Settings, step: usize) -> Settings {
+    let index = PAUSE_SETTINGS.iter().position(|s| *s == setting).unwrap_or_default();
+    PAUSE_SETTINGS[(index + step) % PAUSE_SETTINGS.len()]
+}
+
+fn show_page(
+    pause_menu_state: Res<PauseMenuState>,
+    mut options_page: Query<&mut Style, (With<OptionsPage>, Without<SettingsPage>)>,
+    mut settings_page: Query<&mut Style, (With<SettingsPage>, Without<OptionsPage>)>,
+) {
+    let settings_open = pause_menu_state.settings.is_some();
+    for mut style in &mut options_page {
+        style.display = if settings_open { Display::None } else { Display::Flex };
+    }
+    for mut style in &mut settings_page {
+        style.display = if settings_open { Display::Flex } else { Display::None };
+    }
+}
+
+fn highlight_selection(
+    pause_menu_state: Res<PauseMenuState>,
+    mut options: Query<(&PauseOption, &mut BackgroundColor), Without<Settings>>,
+    mut settings: Query<(&Settings, &mut BackgroundColor), Without<PauseOption>>,
+) {
+    let highlight = |selected| if selected { BackgroundColor(ui::HOVERED_BUTTON) } else { BackgroundColor(ui::NORMAL_BUTTON) };
+    for (option, mut background_color) in &mut options {
+        *background_color = highlight(*option == pause_menu_state.selected);
+    }
+    let Some(settings_state) = pause_menu_state.settings.as_ref() else { return };
+    for (setting, mut background_color) in &mut settings {
+        *background_color = highlight(*setting == settings_state.selected_setting);
+    }
+}
+
+fn update_setting_values(
+    pause_menu_state: Res<PauseMenuState>,
+    mut values: Query<(&SettingValue, &mut Text)>,
+) {
+    let Some(settings_state) = pause_menu_state.settings.as_ref() else { return };
+    for (value, mut text) in &mut values {
+        text.sections[0].value = settings_state.display(&value.0).trim().to_string();
+    }
+}
@@ -1,7 +1,8 @@
 use bevy::prelude::*;
 
+/// A kart controlled by a local player, holding the player's index
 #[derive(Component)]
-pub struct Player;
+pub struct Player(pub usize);
 
 #[derive(Component)]
 #[component(storage = "SparseSet")]
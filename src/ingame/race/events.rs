@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+use crate::ingame::{game_settings, kart, tower};
+
+/// Things that happen during a race. They're sent from where they happen so the
+/// HUD, audio, stats and game modes can listen for them instead of being called directly.
+pub struct RaceEventsPlugin;
+impl Plugin for RaceEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LapCompleted>()
+            .add_event::<KartEliminated>()
+            .add_event::<TowerPlaced>()
+            .add_event::<BulletHit>()
+            .add_event::<PlaceChanged>()
+            .add_event::<RaceFinished>();
+    }
+}
+
+/// A kart crossed the line after driving a lap that counts
+#[derive(Event)]
+pub struct LapCompleted {
+    pub entity: Entity,
+    /// The lap the kart is now on
+    pub lap: usize,
+    /// The kart's place as it crossed the line
+    pub place: usize,
+}
+
+/// A kart ran out of health and was removed from the race
+#[derive(Event)]
+pub struct KartEliminated {
+    pub entity: Entity,
+    pub color: Color,
+    pub player: Option<usize>,
+    pub name: String,
+    pub class: kart::KartClass,
+}
+
+#[derive(Event)]
+pub struct TowerPlaced {
+    pub tower: Entity,
+    pub owner: Entity,
+    pub kind: tower::TowerKind,
+}
+
+/// A bullet hit an enemy kart or tower
+#[derive(Event)]
+pub struct BulletHit {
+    pub owner: Entity,
+    pub target: Entity,
+}
+
+/// Sent when a racer moves up or down the standings
+#[derive(Event)]
+pub struct PlaceChanged {
+    pub entity: Entity,
+    pub previous: usize,
+    pub place: usize,
+}
+
+/// The race is over and the game moves on to the end screen
+#[derive(Event)]
+pub struct RaceFinished {
+    pub ending: game_settings::GameEndingState,
+}
@@ -0,0 +1,82 @@
+use bevy::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+use crate::{IngameState, ingame::{config, controller, kart, path}};
+
+/// Karts slower than this aren't going anywhere so can't be going the wrong way
+const MIN_WRONG_WAY_SPEED: f32 = 2.;
+/// How far from the track's direction the kart's velocity has to point, 1 is straight back
+const WRONG_WAY_DOT: f32 = 0.5;
+
+pub struct LapCheckPlugin;
+impl Plugin for LapCheckPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LapRejected>()
+            .add_systems(
+                Update,
+                (
+                    detect_wrong_way,
+                    hold_penalty_stops.after(controller::ControllerSet::Movement),
+                ).run_if(in_state(IngameState::InGame)),
+            );
+    }
+}
+
+/// Sent when a kart crosses the line without having driven a proper lap
+#[derive(Event)]
+pub struct LapRejected {
+    pub entity: Entity,
+}
+
+/// Tracks how long a kart has been driving against the direction of the track
+#[derive(Component, Default)]
+pub struct WrongWay {
+    time: f32,
+    pub is_active: bool,
+}
+
+/// Keeps a kart in place until the timer runs out
+#[derive(Component)]
+pub struct PenaltyStop(pub Timer);
+
+/// A lap counts when the kart drove most of the track and stayed near the racing line
+pub fn is_lap_valid(place_counter: &super::PlaceCounter, track_length: f32, game_config: &config::GameConfig) -> bool {
+    place_counter.lap_distance >= track_length * game_config.min_lap_fraction
+        && place_counter.off_track_distance <= track_length * game_config.max_off_track_fraction
+}
+
+fn detect_wrong_way(
+    mut karts: Query<(&Transform, &LinearVelocity, &mut WrongWay), With<kart::Kart>>,
+    path_manager: Res<path::PathManager>,
+    game_config: Res<config::GameConfig>,
+    time: Res<Time>,
+) {
+    if path_manager.is_empty() {
+        return;
+    }
+
+    for (transform, linear_velocity, mut wrong_way) in &mut karts {
+        let velocity = linear_velocity.0 * Vec3::new(1., 0., 1.);
+        let progress = path_manager.progress_at(transform.translation);
+        let track_direction = (path_manager.tangent_at(progress) * Vec3::new(1., 0., 1.)).normalize_or_zero();
+        let is_backward = velocity.length() > MIN_WRONG_WAY_SPEED
+                       && velocity.normalize_or_zero().dot(track_direction) < -WRONG_WAY_DOT;
+
+        wrong_way.time = if is_backward { wrong_way.time + time.delta_seconds() } else { 0. };
+        wrong_way.is_active = wrong_way.time >= game_config.wrong_way_time;
+    }
+}
+
+fn hold_penalty_stops(
+    mut commands: Commands,
+    mut karts: Query<(Entity, &mut PenaltyStop, &mut LinearVelocity)>,
+    time: Res<Time>,
+) {
+    for (entity, mut penalty_stop, mut linear_velocity) in &mut karts {
+        if penalty_stop.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<PenaltyStop>();
+        } else {
+            linear_velocity.x = 0.;
+            linear_velocity.z = 0.;
+        }
+    }
+}
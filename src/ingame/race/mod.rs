@@ -1,16 +1,147 @@
 use bevy::{prelude::*, ecs::system::{Command, SystemState}, };
-use crate::{ingame::{assets, player, path, points, race::placement_sensor::Place}, util::audio};
-use crate::AppState;
+use crate::{ingame::{assets, common, config, kart, player, path, points, game_settings, track, race::placement::Place}, util::audio};
+use crate::{AppState, IngameState};
 use bevy_xpbd_3d::{math::*, prelude::*};
 use bevy::render::primitives::Aabb;
+use bevy_kira_audio::prelude::*;
 
-pub mod placement_sensor;
+pub mod events;
+pub mod lap_check;
+pub mod placement;
+pub mod time_trial;
 
 pub struct RacePlugin;
 impl Plugin for RacePlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(placement_sensor::PlacementSensorPlugin);
-        app.add_systems(Update, populate_waypoint_indices.run_if(in_state(AppState::InGame)));
+        app.add_plugins((events::RaceEventsPlugin, lap_check::LapCheckPlugin, placement::PlacementPlugin, time_trial::TimeTrialPlugin));
+        app.init_resource::<RaceResults>()
+            .add_systems(OnEnter(AppState::InGame), reset_results)
+            .add_systems(Update, populate_waypoint_indices.run_if(in_state(AppState::InGame)))
+            .add_systems(Update, (check_race_finished, end_race).chain().run_if(in_state(IngameState::InGame)))
+            .add_systems(Update, (award_lap_points, play_lap_sfx, record_knockouts).run_if(in_state(AppState::InGame)));
+    }
+}
+
+/// Set on a kart once it completes the last lap, it keeps driving but stops counting laps
+#[derive(Component)]
+pub struct Finished;
+
+#[derive(Clone, Copy)]
+pub enum RaceOutcome {
+    /// Seconds into the race the kart crossed the line
+    Finished(f32),
+    /// Still racing when the race ended, on this lap
+    Unfinished(usize),
+    KnockedOut,
+}
+
+#[derive(Clone)]
+pub struct RaceResult {
+    pub color: Color,
+    pub player: Option<usize>,
+    pub name: String,
+    pub class: kart::KartClass,
+    pub outcome: RaceOutcome,
+}
+
+/// Karts that finished, in the order they crossed the line, and karts that were knocked out
+#[derive(Resource, Default)]
+pub struct RaceResults {
+    finished: Vec<RaceResult>,
+    knocked_out: Vec<RaceResult>,
+    /// Started by the first kart to finish, whoever is still racing when it runs out doesn't finish
+    finish_timeout: Option<Timer>,
+}
+
+impl RaceResults {
+    fn add_knocked_out(&mut self, color: Color, player: Option<usize>, name: String, class: kart::KartClass) {
+        self.knocked_out.push(RaceResult { color, player, name, class, outcome: RaceOutcome::KnockedOut });
+    }
+
+    /// Full standings given the karts still racing, which should already be sorted by place
+    pub fn standings(&self, racing: impl Iterator<Item = (Color, Option<usize>, String, kart::KartClass, usize)>) -> Vec<RaceResult> {
+        self.finished.iter()
+            .cloned()
+            .chain(racing.map(|(color, player, name, class, lap)| RaceResult { color, player, name, class, outcome: RaceOutcome::Unfinished(lap) }))
+            // the last kart knocked out lasted the longest
+            .chain(self.knocked_out.iter().rev().cloned())
+            .collect()
+    }
+}
+
+fn reset_results(mut race_results: ResMut<RaceResults>) {
+    *race_results = RaceResults::default();
+}
+
+fn check_race_finished(
+    game_state: Res<game_settings::GameState>,
+    mut race_results: ResMut<RaceResults>,
+    mut race_finished_event_writer: EventWriter<events::RaceFinished>,
+    racers: Query<Has<Finished>, With<kart::Kart>>,
+    time: Res<Time>,
+) {
+    let Some(finish_timeout) = race_results.finish_timeout.as_mut() else { return };
+    let has_timed_out = finish_timeout.tick(time.delta()).finished();
+    let everyone_finished = racers.iter().all(|is_finished| is_finished);
+
+    if has_timed_out || everyone_finished {
+        let winner_is_player = race_results.finished.first().map_or(false, |winner| winner.player.is_some());
+        race_finished_event_writer.send(events::RaceFinished {
+            ending: if winner_is_player && !game_state.time_trial {
+                game_settings::GameEndingState::Winner
+            } else {
+                game_settings::GameEndingState::Finished
+            },
+        });
+    }
+}
+
+fn end_race(
+    mut race_finished_event_reader: EventReader<events::RaceFinished>,
+    mut game_state: ResMut<game_settings::GameState>,
+    mut next_ingame_state: ResMut<NextState<IngameState>>,
+    mut game_audio: audio::GameAudio,
+    kira_audio: Res<Audio>,
+) {
+    if let Some(race_finished) = race_finished_event_reader.read().next() {
+        game_state.ending_state = race_finished.ending;
+        game_audio.stop_bgm();
+        kira_audio.stop();
+        next_ingame_state.set(IngameState::EndGame);
+    }
+}
+
+fn award_lap_points(
+    mut lap_completed_event_reader: EventReader<events::LapCompleted>,
+    mut racers: Query<&mut points::Points>,
+) {
+    for lap_completed in lap_completed_event_reader.read() {
+        if let Ok(mut points) = racers.get_mut(lap_completed.entity) {
+            points.0 += 9usize.saturating_sub(lap_completed.place);
+        }
+    }
+}
+
+fn play_lap_sfx(
+    mut lap_completed_event_reader: EventReader<events::LapCompleted>,
+    players: Query<(), With<player::Player>>,
+    game_assets: Res<assets::GameAssets>,
+    mut game_audio: audio::GameAudio,
+) {
+    let player_completed_lap = lap_completed_event_reader
+        .read()
+        .any(|lap_completed| players.contains(lap_completed.entity));
+    if player_completed_lap {
+        game_audio.play_sfx(&game_assets.sfx_lap);
+    }
+}
+
+fn record_knockouts(
+    mut kart_eliminated_event_reader: EventReader<events::KartEliminated>,
+    mut race_results: ResMut<RaceResults>,
+) {
+    for kart_eliminated in kart_eliminated_event_reader.read() {
+        race_results.add_knocked_out(kart_eliminated.color, kart_eliminated.player, kart_eliminated.name.clone(), kart_eliminated.class);
     }
 }
 
@@ -42,8 +173,16 @@ pub struct NextWayPoint(pub WayPoints);
 #[derive(Component)]
 pub struct LapCounter(pub usize);
 
-#[derive(Component)]
-pub struct PlaceCounter(pub usize);
+/// How far a racer has driven along the track since it started its current lap
+#[derive(Component, Default)]
+pub struct PlaceCounter {
+    pub lap_distance: f32,
+    /// Part of `lap_distance` that was driven away from the racing line
+    pub off_track_distance: f32,
+    last_progress: Option<f32>,
+    /// Order the kart was spawned in, breaks ties between karts at the same distance
+    pub spawn_index: usize,
+}
 
 pub struct WayPointSpawner {
     pub entity: Entity,
@@ -82,22 +221,64 @@ pub struct WayPointHitHandler {
 impl Command for WayPointHitHandler {
     fn apply(self, world: &mut World) {
         let mut system_state: SystemState<(
-            Query<(&mut NextWayPoint, &mut LapCounter, &mut PlaceCounter, &mut points::Points, &Place, Has<player::Player>)>,
-            Res<assets::GameAssets>,
-            audio::GameAudio,
+            Query<(&mut NextWayPoint, &mut LapCounter, &mut PlaceCounter, &mut points::Points, &Place, Option<&mut time_trial::LapTimer>), Without<Finished>>,
+            Res<game_settings::GameState>,
+            Res<config::GameConfig>,
+            Res<path::PathManager>,
+            EventWriter<common::health::HealthHitEvent>,
+            EventWriter<lap_check::LapRejected>,
+            EventWriter<events::LapCompleted>,
         )> = SystemState::new(world);
 
-        let (mut next_waypoints,game_assets, mut audio) = system_state.get_mut(world);
+        let (mut next_waypoints, game_state, game_config, path_manager, mut health_hit_event_writer, mut lap_rejected_event_writer, mut lap_completed_event_writer) = system_state.get_mut(world);
+        let track = track::get(game_state.track);
+        let mut finished_lap = None;
+        let mut penalty_stop = None;
+        let mut has_finished = false;
+        let race_laps = game_state.race_laps();
+        let game_time = game_state.game_time;
+        let finish_timeout = game_config.finish_timeout;
+
+        if let Ok((mut next_waypoint, mut lap_counter, mut place_counter, mut points, place, lap_timer)) = next_waypoints.get_mut(self.entity) {
+            let is_lap_done = next_waypoint.0 == WayPoints::Start;
+            let is_lap_valid = !is_lap_done || lap_check::is_lap_valid(&place_counter, path_manager.length(), &game_config);
+
+            if let Some(mut lap_timer) = lap_timer {
+                if is_lap_valid {
+                    finished_lap = lap_timer.split(game_state.game_time, is_lap_done);
+                } else {
+                    lap_timer.discard(game_state.game_time);
+                }
+            }
 
-        if let Ok((mut next_waypoint, mut lap_counter, mut place_counter, mut points, place, is_player)) = next_waypoints.get_mut(self.entity) {
             next_waypoint.0 = match next_waypoint.0 {
+                WayPoints::Start if !is_lap_valid => {
+                    // the kart has to drive a whole lap again before it counts
+                    place_counter.lap_distance = 0.;
+                    place_counter.off_track_distance = 0.;
+                    match game_config.lap_penalty {
+                        config::LapPenalty::Time => penalty_stop = Some(game_config.lap_penalty_amount as f32),
+                        config::LapPenalty::Credits => points.0 = points.0.saturating_sub(game_config.lap_penalty_amount),
+                        config::LapPenalty::Health => health_hit_event_writer.send(common::health::HealthHitEvent {
+                            entity: self.entity,
+                            hit_points: game_config.lap_penalty_amount,
+                        }),
+                    }
+                    lap_rejected_event_writer.send(lap_check::LapRejected { entity: self.entity });
+
+                    WayPoints::Quarter
+                },
                 WayPoints::Start => {
                     lap_counter.0 += 1;
-                    place_counter.0 = 0;
-                    points.0 += 9 - place.0;
-                    if is_player {
-                        audio.play_sfx(&game_assets.sfx_lap);
-                    }
+                    place_counter.lap_distance = 0.;
+                    place_counter.off_track_distance = 0.;
+                    lap_completed_event_writer.send(events::LapCompleted {
+                        entity: self.entity,
+                        lap: lap_counter.0,
+                        place: place.0,
+                    });
+                    // lap counters start at 1 so a kart finishes by moving past the target
+                    has_finished = race_laps.map_or(false, |laps| lap_counter.0 > laps);
 
                     WayPoints::Quarter
                 },
@@ -106,5 +287,25 @@ impl Command for WayPointHitHandler {
                 WayPoints::Finish => WayPoints::Start,
             };
         }
+
+        if has_finished {
+            let color = world.get::<kart::Kart>(self.entity).map_or(Color::WHITE, |kart| kart.0);
+            let player = world.get::<player::Player>(self.entity).map(|player| player.0);
+            let name = world.get::<kart::KartName>(self.entity).map_or(String::new(), |name| name.0.clone());
+            let class = world.get::<kart::KartClass>(self.entity).copied().unwrap_or_default();
+            world.entity_mut(self.entity).insert(Finished);
+
+            let mut race_results = world.resource_mut::<RaceResults>();
+            race_results.finished.push(RaceResult { color, player, name, class, outcome: RaceOutcome::Finished(game_time) });
+            race_results.finish_timeout.get_or_insert_with(|| Timer::from_seconds(finish_timeout, TimerMode::Once));
+        }
+
+        if let Some(seconds) = penalty_stop {
+            world.entity_mut(self.entity).insert(lap_check::PenaltyStop(Timer::from_seconds(seconds, TimerMode::Once)));
+        }
+
+        if let Some((lap, ghost)) = finished_lap {
+            world.resource_mut::<time_trial::BestLaps>().submit(track, lap, ghost);
+        }
     }
 }
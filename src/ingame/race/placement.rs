@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use crate::{AppState, ingame::assets, ingame::config, ingame::path, ingame::race, ingame::race::events::{PlaceChanged, RaceFinished}, ingame::player, ingame::common, ingame::game_settings, util::audio};
+
+/// Moves bigger than this many path points in one frame are ignored, they
+/// happen when the closest part of the track jumps to a section nearby
+const MAX_STEP_POINTS: f32 = 4.;
+
+pub struct PlacementPlugin;
+impl Plugin for PlacementPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (track_lap_distance, play_overtake_sfx).run_if(in_state(AppState::InGame)))
+            .add_systems(
+                FixedUpdate,
+                (update_places,).run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+#[derive(Component)]
+pub struct Place(pub usize);
+
+fn track_lap_distance(
+    mut racers: Query<(&Transform, &mut race::PlaceCounter)>,
+    path_manager: Res<path::PathManager>,
+    game_config: Res<config::GameConfig>,
+) {
+    if path_manager.is_empty() {
+        return;
+    }
+
+    let length = path_manager.length();
+    let max_step = path_manager.point_spacing() * MAX_STEP_POINTS;
+    for (transform, mut place_counter) in &mut racers {
+        let progress = path_manager.progress_at(transform.translation);
+        if let Some(last_progress) = place_counter.last_progress {
+            let forward = path_manager.distance_between(last_progress, progress);
+            // driving the wrong way counts against the racer
+            let step = if forward > length * 0.5 { forward - length } else { forward };
+            if step.abs() < max_step {
+                place_counter.lap_distance += step;
+
+                let racing_line = path_manager.point_at(progress);
+                let off_track = (transform.translation - racing_line) * Vec3::new(1., 0., 1.);
+                if step > 0. && off_track.length() > game_config.off_track_distance {
+                    place_counter.off_track_distance += step;
+                }
+            }
+        }
+        place_counter.last_progress = Some(progress);
+    }
+}
+
+fn update_places(
+    mut commands: Commands,
+    mut racers: Query<(Entity, &race::LapCounter, &race::PlaceCounter, Option<&mut Place>, Has<player::Player>)>,
+    mut place_changed_event_writer: EventWriter<PlaceChanged>,
+    mut health_hit_event_writer: EventWriter<common::health::HealthHitEvent>,
+    mut race_finished_event_writer: EventWriter<RaceFinished>,
+    game_state: Res<game_settings::GameState>,
+) {
+    let mut standings = racers.iter()
+                              .map(|(entity, lap_counter, place_counter, _, is_player)| (entity, lap_counter.0, place_counter.lap_distance, place_counter.spawn_index, is_player))
+                              .collect::<Vec<_>>();
+    // ties go to the earlier spawned kart so places don't flicker between frames
+    standings.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.total_cmp(&a.2)).then(a.3.cmp(&b.3)));
+    let furthest_lap = standings.first().map(|(_, lap, _, _, _)| *lap).unwrap_or(0);
+
+    for (i, (e, lap, _, _, is_player)) in standings.iter().enumerate() {
+        let place = i + 1;
+        if let Ok((_, _, _, current_place, _)) = racers.get_mut(*e) {
+            match current_place {
+                Some(mut current_place) if current_place.0 != place => {
+                    place_changed_event_writer.send(PlaceChanged {
+                        entity: *e,
+                        previous: current_place.0,
+                        place,
+                    });
+                    current_place.0 = place;
+                },
+                Some(_) => (),
+                None => {
+                    commands.entity(*e).insert(Place(place));
+                },
+            }
+        }
+
+        if *lap < furthest_lap.saturating_sub(1) { // kart fell behind
+            #[cfg(not(feature = "endless"))]
+            {
+                health_hit_event_writer.send(common::health::HealthHitEvent {
+                    entity: *e,
+                    hit_points: 10
+                });
+
+                // with several players the hit above knocks them out and the race carries on
+                if *is_player && game_state.number_of_players == 1 {
+                    race_finished_event_writer.send(RaceFinished {
+                        ending: game_settings::GameEndingState::FellBehind,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn play_overtake_sfx(
+    mut place_changed_event_reader: EventReader<PlaceChanged>,
+    players: Query<(), With<player::Player>>,
+    game_assets: Res<assets::GameAssets>,
+    mut game_audio: audio::GameAudio,
+) {
+    let has_overtaken = place_changed_event_reader
+        .read()
+        .any(|event| event.place < event.previous && players.contains(event.entity));
+    if has_overtaken {
+        game_audio.play_sfx(&game_assets.sfx_1);
+    }
+}
@@ -0,0 +1,283 @@
+use bevy::{prelude::*, gltf::Gltf, pbr::NotShadowCaster};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::{assets, ingame::{game_settings, player, track, CleanupMarker}, util, IngameState};
+
+#[cfg(not(feature = "web"))]
+const SAVE_DIRECTORY: &str = "saves";
+#[cfg(not(feature = "web"))]
+const BEST_LAPS_PATH: &str = "saves/best_laps.ron";
+const GHOST_SAMPLE_INTERVAL: f32 = 0.05;
+
+pub struct TimeTrialPlugin;
+impl Plugin for TimeTrialPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BestLaps::load())
+            .add_systems(OnEnter(IngameState::PreGame), spawn_ghost.run_if(is_time_trial))
+            .add_systems(Update, (add_lap_timers, record_ghost_samples, move_ghost).run_if(in_state(IngameState::InGame).and_then(is_time_trial)));
+    }
+}
+
+pub fn is_time_trial(game_state: Res<game_settings::GameState>) -> bool {
+    game_state.time_trial
+}
+
+/// Formats seconds as `m:ss.cc`
+pub fn format_time(seconds: f32) -> String {
+    let hundredths = (seconds.max(0.) * 100.).round() as usize;
+    format!("{}:{:02}.{:02}", hundredths / 6000, (hundredths / 100) % 60, hundredths % 100)
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GhostSample {
+    pub time: f32,
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LapTime {
+    pub time: f32,
+    /// Time between each waypoint, the last one ends at the start line
+    pub sectors: Vec<f32>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BestLap {
+    pub lap: LapTime,
+    pub ghost: Vec<GhostSample>,
+}
+
+/// Best lap per track name, saved to `saves/best_laps.ron`
+#[derive(Default, Resource, Serialize, Deserialize)]
+pub struct BestLaps(pub HashMap<String, BestLap>);
+
+impl BestLaps {
+    #[cfg(not(feature = "web"))]
+    fn load() -> Self {
+        std::fs::read_to_string(BEST_LAPS_PATH)
+            .ok()
+            .and_then(|contents| ron::from_str(&contents).map_err(|e| warn!("couldn't read best laps: {}", e)).ok())
+            .unwrap_or_default()
+    }
+
+    #[cfg(feature = "web")]
+    fn load() -> Self {
+        BestLaps::default()
+    }
+
+    fn save(&self) {
+        #[cfg(not(feature = "web"))]
+        {
+            let result = std::fs::create_dir_all(SAVE_DIRECTORY)
+                .map_err(|e| e.to_string())
+                .and_then(|_| ron::to_string(self).map_err(|e| e.to_string()))
+                .and_then(|contents| std::fs::write(BEST_LAPS_PATH, contents).map_err(|e| e.to_string()));
+
+            if let Err(error) = result {
+                warn!("couldn't save best laps: {}", error);
+            }
+        }
+    }
+
+    pub fn get(&self, track: track::Track) -> Option<&BestLap> {
+        self.0.get(track.name)
+    }
+
+    /// Keeps the lap if it's the fastest one on the track, returns true if it was
+    pub fn submit(&mut self, track: track::Track, lap: LapTime, ghost: Vec<GhostSample>) -> bool {
+        let is_best = self.get(track).map_or(true, |best| lap.time < best.lap.time);
+        if is_best {
+            self.0.insert(track.name.to_string(), BestLap { lap, ghost });
+            self.save();
+        }
+
+        is_best
+    }
+}
+
+/// Lap and sector times for a kart in a time trial. Splits are taken by
+/// `WayPointHitHandler` each time the kart reaches its next waypoint.
+#[derive(Component, Default)]
+pub struct LapTimer {
+    pub lap_start: f32,
+    sector_start: f32,
+    sectors: Vec<f32>,
+    ghost: Vec<GhostSample>,
+    pub last_sector: Option<f32>,
+    pub laps: Vec<LapTime>,
+}
+
+impl LapTimer {
+    /// Returns the finished lap and its ghost when `lap_completed` is set
+    pub fn split(&mut self, now: f32, lap_completed: bool) -> Option<(LapTime, Vec<GhostSample>)> {
+        let sector = now - self.sector_start;
+        self.sector_start = now;
+        self.sectors.push(sector);
+        self.last_sector = Some(sector);
+
+        if !lap_completed {
+            return None;
+        }
+
+        let lap = LapTime {
+            time: now - self.lap_start,
+            sectors: std::mem::take(&mut self.sectors),
+        };
+        self.lap_start = now;
+        self.laps.push(lap.clone());
+
+        Some((lap, std::mem::take(&mut self.ghost)))
+    }
+
+    /// Throws away a lap that didn't count and starts timing the next one
+    pub fn discard(&mut self, now: f32) {
+        self.lap_start = now;
+        self.sector_start = now;
+        self.sectors.clear();
+        self.ghost.clear();
+        self.last_sector = None;
+    }
+
+    pub fn fastest_lap(&self) -> Option<&LapTime> {
+        self.laps.iter().min_by(|a, b| a.time.total_cmp(&b.time))
+    }
+}
+
+fn add_lap_timers(
+    mut commands: Commands,
+    players: Query<Entity, (With<player::Player>, Without<LapTimer>)>,
+) {
+    for entity in &players {
+        commands.entity(entity).insert(LapTimer::default());
+    }
+}
+
+fn record_ghost_samples(
+    mut lap_timers: Query<(&mut LapTimer, &Transform)>,
+    game_state: Res<game_settings::GameState>,
+) {
+    for (mut lap_timer, transform) in &mut lap_timers {
+        let time = game_state.game_time - lap_timer.lap_start;
+        let is_due = lap_timer.ghost.last().map_or(true, |sample| time - sample.time >= GHOST_SAMPLE_INTERVAL);
+        if is_due {
+            lap_timer.ghost.push(GhostSample {
+                time,
+                translation: transform.translation.to_array(),
+                rotation: transform.rotation.to_array(),
+            });
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct GhostKart(Vec<GhostSample>);
+
+fn spawn_ghost(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    game_assets: Res<assets::GameAssets>,
+    assets_gltf: Res<Assets<Gltf>>,
+    best_laps: Res<BestLaps>,
+    game_state: Res<game_settings::GameState>,
+) {
+    let Some(best_lap) = best_laps.get(track::get(game_state.track)) else { return };
+    let Some(first) = best_lap.ghost.first() else { return };
+    let Some(gltf) = assets_gltf.get(&game_assets.car) else { return };
+
+    let ghost_material = materials.add(StandardMaterial {
+        base_color: Color::rgba(1.0, 1.0, 1.0, 0.35),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    });
+
+    commands.spawn((
+        util::scene_hook::HookedSceneBundle {
+            scene: SceneBundle {
+                scene: gltf.scenes[0].clone(),
+                transform: Transform::from_translation(Vec3::from_array(first.translation))
+                    .with_rotation(Quat::from_array(first.rotation)),
+                ..default()
+            },
+            hook: util::scene_hook::SceneHook::new(move |cmds, hook_data| {
+                if hook_data.mesh.is_some() {
+                    cmds.insert((ghost_material.clone(), NotShadowCaster));
+                }
+            }),
+        },
+        GhostKart(best_lap.ghost.clone()),
+        CleanupMarker,
+    ));
+}
+
+/// Plays the best lap back alongside the player's current lap
+fn move_ghost(
+    mut ghosts: Query<(&GhostKart, &mut Transform, &mut Visibility)>,
+    lap_timers: Query<&LapTimer, With<player::Player>>,
+    game_state: Res<game_settings::GameState>,
+) {
+    let Some(lap_timer) = lap_timers.iter().next() else { return };
+    let time = game_state.game_time - lap_timer.lap_start;
+
+    for (ghost, mut transform, mut visibility) in &mut ghosts {
+        let next = ghost.0.partition_point(|sample| sample.time <= time);
+        if next == 0 || next >= ghost.0.len() {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+
+        let (from, to) = (&ghost.0[next - 1], &ghost.0[next]);
+        let t = ((time - from.time) / (to.time - from.time)).clamp(0., 1.);
+        transform.translation = Vec3::from_array(from.translation).lerp(Vec3::from_array(to.translation), t);
+        transform.rotation = Quat::from_array(from.rotation).slerp(Quat::from_array(to.rotation), t);
+        *visibility = Visibility::Inherited;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_times_sectors_until_the_lap_is_done() {
+        let mut lap_timer = LapTimer::default();
+        assert!(lap_timer.split(10., false).is_none());
+        assert!(lap_timer.split(25., false).is_none());
+        assert_eq!(lap_timer.last_sector, Some(15.));
+
+        let (lap, _) = lap_timer.split(40., true).unwrap();
+        assert_eq!(lap.time, 40.);
+        assert_eq!(lap.sectors, vec![10., 15., 15.]);
+    }
+
+    #[test]
+    fn split_starts_the_next_lap_where_the_last_one_ended() {
+        let mut lap_timer = LapTimer::default();
+        lap_timer.split(30., true);
+        let (lap, _) = lap_timer.split(50., true).unwrap();
+        assert_eq!(lap.time, 20.);
+        assert_eq!(lap.sectors, vec![20.]);
+        assert_eq!(lap_timer.laps.len(), 2);
+        assert_eq!(lap_timer.fastest_lap().map(|lap| lap.time), Some(20.));
+    }
+
+    #[test]
+    fn split_hands_over_the_ghost() {
+        let mut lap_timer = LapTimer::default();
+        lap_timer.ghost.push(GhostSample { time: 0., translation: [0.; 3], rotation: [0., 0., 0., 1.] });
+        let (_, ghost) = lap_timer.split(5., true).unwrap();
+        assert_eq!(ghost.len(), 1);
+        assert!(lap_timer.ghost.is_empty());
+    }
+
+    #[test]
+    fn discard_restarts_the_lap() {
+        let mut lap_timer = LapTimer::default();
+        lap_timer.split(10., false);
+        lap_timer.discard(12.);
+        let (lap, _) = lap_timer.split(20., true).unwrap();
+        assert_eq!(lap.time, 8.);
+        assert_eq!(lap.sectors, vec![8.]);
+    }
+}
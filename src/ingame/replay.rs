@@ -0,0 +1,493 @@
+use bevy::{prelude::*, ecs::{query::Has, system::{Command, SystemState}}, time::TimeUpdateStrategy, window::{PresentMode, PrimaryWindow}, utils::HashMap};
+use bevy_turborand::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use smooth_bevy_cameras::{controllers::fps::{FpsCameraBundle, FpsCameraController}, LookTransform, Smoother};
+use std::time::Duration;
+use crate::{assets, ui, AppState, IngameState};
+use crate::assets::command_ext::*;
+use super::{bot, camera, controller, game_settings, kart, CleanupMarker};
+
+#[cfg(not(feature = "web"))]
+const REPLAY_DIRECTORY: &str = "replays";
+#[cfg(not(feature = "web"))]
+const REPLAY_PATH: &str = "replays/last.replay.ron";
+const SEEK_SECONDS: f32 = 10.;
+// kart positions are stored every so often so debug builds can check that
+// playback hasn't drifted away from the recording
+const SNAPSHOT_FRAMES: usize = 30;
+#[cfg(debug_assertions)]
+const DESYNC_TOLERANCE: f32 = 0.01;
+
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Replay>()
+            .configure_sets(Update, controller::ControllerSet::Input.run_if(not(is_playing_back)))
+            // bots have to send their movement before it's recorded and applied in the same frame
+            .configure_sets(Update, bot::BotSet.run_if(not(is_playing_back)).before(controller::ControllerSet::Movement))
+            .configure_sets(FixedUpdate, bot::BotSet.run_if(not(is_playing_back)))
+            .add_systems(PreUpdate, start_frame.run_if(in_state(AppState::InGame).and_then(not(in_state(IngameState::EndGame)))))
+            .add_systems(
+                Update,
+                (record_inputs.run_if(is_recording), play_back_frame.run_if(is_playing_back))
+                    .after(controller::ControllerSet::Input)
+                    .after(bot::BotSet)
+                    .before(controller::ControllerSet::Movement)
+                    .run_if(in_state(AppState::InGame).and_then(not(in_state(IngameState::EndGame)))),
+            )
+            .add_systems(Update, (handle_playback_input, update_replay_text).run_if(in_state(AppState::InGame).and_then(is_playing_back)))
+            .add_systems(Last, record_karts.run_if(in_state(AppState::InGame).and_then(not(in_state(IngameState::EndGame))).and_then(is_recording)))
+            .add_systems(Last, finish_frame.run_if(in_state(AppState::InGame).and_then(not(in_state(IngameState::EndGame))).and_then(is_playing_back)))
+            .add_systems(OnEnter(IngameState::PreGame), spawn_replay_text.run_if(is_playing_back))
+            .add_systems(OnEnter(IngameState::EndGame), (save_replay.run_if(is_recording), stop_playback.run_if(is_playing_back)));
+
+        #[cfg(debug_assertions)]
+        app.add_systems(Last, assert_in_sync.before(finish_frame).run_if(in_state(AppState::InGame).and_then(not(in_state(IngameState::EndGame))).and_then(is_playing_back)));
+    }
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+pub enum ReplayMode {
+    #[default]
+    Recording,
+    Playback,
+}
+
+/// Everything needed to play a race back. Karts are identified by the index of
+/// their color in [`game_settings::GameState`]'s `kart_colors`. The race is run
+/// again from the same seed with every frame taking exactly as long as it did
+/// and the same inputs sent at the same point of each frame.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct ReplayRecording {
+    pub seed: u64,
+    pub track: usize,
+    pub number_of_players: usize,
+    #[serde(default)]
+    pub time_trial: bool,
+    #[serde(default)]
+    pub laps: Option<usize>,
+    #[serde(default)]
+    pub kart_class: kart::KartClass,
+    /// Missing from replays recorded before players could pick these
+    #[serde(default)]
+    pub player_name: Option<String>,
+    #[serde(default)]
+    pub player_color: Option<usize>,
+    pub frames: Vec<ReplayFrame>,
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    /// Kept as a `Duration` since rounding it would change how often the fixed
+    /// timestep and physics run during playback
+    pub delta: Duration,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub movements: Vec<(usize, controller::MovementAction)>,
+    /// In the order they were applied
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<(usize, controller::KartAction)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub karts: Vec<KartSnapshot>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct KartSnapshot {
+    pub kart: usize,
+    pub translation: [f32; 3],
+}
+
+#[derive(Default, Resource)]
+pub struct Replay {
+    pub mode: ReplayMode,
+    pub recording: ReplayRecording,
+    playback_requested: bool,
+    seek_to: Option<usize>,
+    current: usize,
+}
+
+impl ReplayRecording {
+    /// Seconds into the race at the end of the given frame
+    fn elapsed(&self, frame: usize) -> f32 {
+        self.frames.iter().take(frame + 1).map(|f| f.delta.as_secs_f32()).sum()
+    }
+
+    fn frame_at(&self, seconds: f32) -> usize {
+        let mut elapsed = 0.;
+        for (i, frame) in self.frames.iter().enumerate() {
+            elapsed += frame.delta.as_secs_f32();
+            if elapsed >= seconds {
+                return i;
+            }
+        }
+
+        self.frames.len().saturating_sub(1)
+    }
+
+    #[cfg(not(feature = "web"))]
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        ron::from_str(&contents).map_err(|e| e.to_string())
+    }
+}
+
+pub fn is_recording(replay: Res<Replay>) -> bool {
+    replay.mode == ReplayMode::Recording
+}
+
+pub fn is_playing_back(replay: Res<Replay>) -> bool {
+    replay.mode == ReplayMode::Playback
+}
+
+/// Reads `--replay <file>` from the command line
+#[cfg(not(feature = "web"))]
+pub fn from_args() -> Option<ReplayRecording> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--replay").skip(1);
+    let path = args.next()?;
+    match ReplayRecording::load(&path) {
+        Ok(recording) => Some(recording),
+        Err(error) => {
+            warn!("couldn't load replay {}: {}", path, error);
+            None
+        }
+    }
+}
+
+fn kart_index(color: Color) -> Option<usize> {
+    game_settings::GameState::default().kart_colors.iter().position(|c| *c == color)
+}
+
+/// Runs before `ingame::setup` so the karts and everything after them draw from
+/// the same seed whether the race is being recorded or played back.
+pub fn start_race(
+    mut replay: ResMut<Replay>,
+    mut global_rng: ResMut<GlobalRng>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    mut physics_loop: ResMut<PhysicsLoop>,
+    game_state: Res<game_settings::GameState>,
+    time: Res<Time>,
+) {
+    if replay.playback_requested {
+        replay.playback_requested = false;
+        replay.mode = ReplayMode::Playback;
+    } else {
+        replay.mode = ReplayMode::Recording;
+        replay.recording = ReplayRecording {
+            seed: global_rng.u64(..),
+            track: game_state.track,
+            number_of_players: game_state.number_of_players,
+            time_trial: game_state.time_trial,
+            laps: game_state.laps,
+            kart_class: game_state.kart_class,
+            player_name: Some(game_state.player_name.clone()),
+            player_color: Some(game_state.player_color),
+            frames: vec![ReplayFrame { delta: time.delta(), ..default() }],
+        };
+    }
+
+    replay.current = 0;
+    *global_rng = GlobalRng::with_seed(replay.recording.seed);
+
+    // leftover time in the fixed timestep accumulators would shift when bots
+    // place towers and when physics steps
+    *fixed_time = Time::<Fixed>::from_duration(fixed_time.timestep());
+    *physics_loop = PhysicsLoop::default();
+}
+
+fn start_frame(mut replay: ResMut<Replay>, time: Res<Time>) {
+    if replay.mode == ReplayMode::Recording {
+        replay.recording.frames.push(ReplayFrame { delta: time.delta(), ..default() });
+    }
+    replay.current += 1;
+}
+
+fn record_inputs(
+    mut replay: ResMut<Replay>,
+    mut movement_event_reader: EventReader<controller::MovementEvent>,
+    mut kart_action_event_reader: EventReader<controller::KartActionEvent>,
+    karts: Query<&kart::Kart>,
+) {
+    let index_of = |entity: Entity| karts.get(entity).ok().and_then(|kart| kart_index(kart.0));
+    let movements = movement_event_reader
+        .read()
+        .filter_map(|event| index_of(event.entity).map(|index| (index, event.action)))
+        .collect::<Vec<_>>();
+    let actions = kart_action_event_reader
+        .read()
+        .filter_map(|event| index_of(event.entity).map(|index| (index, event.action)))
+        .collect::<Vec<_>>();
+
+    if let Some(frame) = replay.recording.frames.last_mut() {
+        frame.movements.extend(movements);
+        frame.actions.extend(actions);
+    }
+}
+
+fn record_karts(
+    mut replay: ResMut<Replay>,
+    karts: Query<(&kart::Kart, &Transform)>,
+) {
+    if replay.current % SNAPSHOT_FRAMES != 0 {
+        return;
+    }
+
+    let snapshots = karts
+        .iter()
+        .filter_map(|(kart, transform)| {
+            kart_index(kart.0).map(|index| KartSnapshot {
+                kart: index,
+                translation: transform.translation.to_array(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(frame) = replay.recording.frames.last_mut() {
+        frame.karts = snapshots;
+    }
+}
+
+fn play_back_frame(
+    replay: Res<Replay>,
+    mut movement_event_writer: EventWriter<controller::MovementEvent>,
+    mut kart_action_event_writer: EventWriter<controller::KartActionEvent>,
+    karts: Query<(Entity, &kart::Kart)>,
+) {
+    let Some(frame) = replay.recording.frames.get(replay.current) else { return };
+    let karts = karts
+        .iter()
+        .filter_map(|(entity, kart)| kart_index(kart.0).map(|index| (index, entity)))
+        .collect::<HashMap<_, _>>();
+
+    for (index, action) in frame.movements.iter() {
+        if let Some(entity) = karts.get(index) {
+            movement_event_writer.send(controller::MovementEvent { entity: *entity, action: *action });
+        }
+    }
+
+    for (index, action) in frame.actions.iter() {
+        if let Some(entity) = karts.get(index) {
+            kart_action_event_writer.send(controller::KartActionEvent { entity: *entity, action: *action });
+        }
+    }
+}
+
+/// Playback runs the same simulation as the recording, so a kart that isn't
+/// where it was recorded means something in the race isn't deterministic
+#[cfg(debug_assertions)]
+fn assert_in_sync(
+    replay: Res<Replay>,
+    karts: Query<(&kart::Kart, &Transform)>,
+) {
+    let Some(frame) = replay.recording.frames.get(replay.current) else { return };
+    for snapshot in frame.karts.iter() {
+        if let Some((_, transform)) = karts.iter().find(|(kart, _)| kart_index(kart.0) == Some(snapshot.kart)) {
+            let drift = transform.translation.distance(Vec3::from_array(snapshot.translation));
+            assert!(
+                drift <= DESYNC_TOLERANCE,
+                "replay diverged at frame {}: kart {} is {} from where it was recorded",
+                replay.current, snapshot.kart, drift
+            );
+        }
+    }
+}
+
+fn finish_frame(
+    mut replay: ResMut<Replay>,
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut next_ingame_state: ResMut<NextState<IngameState>>,
+) {
+    if replay.seek_to.map_or(false, |target| replay.current >= target) {
+        replay.seek_to = None;
+        for mut window in &mut windows {
+            window.present_mode = PresentMode::AutoVsync;
+        }
+    }
+
+    match replay.recording.frames.get(replay.current + 1) {
+        Some(next) => *time_update_strategy = TimeUpdateStrategy::ManualDuration(next.delta),
+        None => next_ingame_state.set(IngameState::EndGame),
+    }
+}
+
+fn stop_playback(
+    mut replay: ResMut<Replay>,
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    replay.seek_to = None;
+    *time_update_strategy = TimeUpdateStrategy::Automatic;
+    for mut window in &mut windows {
+        window.present_mode = PresentMode::AutoVsync;
+    }
+}
+
+fn save_replay(replay: Res<Replay>) {
+    #[cfg(not(feature = "web"))]
+    {
+        let result = std::fs::create_dir_all(REPLAY_DIRECTORY)
+            .map_err(|e| e.to_string())
+            .and_then(|_| ron::to_string(&replay.recording).map_err(|e| e.to_string()))
+            .and_then(|contents| std::fs::write(REPLAY_PATH, contents).map_err(|e| e.to_string()));
+
+        match result {
+            Ok(_) => info!("saved replay to {}", REPLAY_PATH),
+            Err(error) => warn!("couldn't save replay: {}", error),
+        }
+    }
+
+    #[cfg(feature = "web")]
+    let _ = replay;
+}
+
+/// Restarts the race from the recording. The replay stays in memory so it can
+/// be watched again from the end screen.
+pub struct WatchReplay {
+    pub recording: Option<ReplayRecording>,
+    pub seek_to: Option<usize>,
+}
+impl Command for WatchReplay {
+    fn apply(self, world: &mut World) {
+        let mut system_state: SystemState<(
+            ResMut<Replay>,
+            ResMut<game_settings::GameState>,
+            ResMut<TimeUpdateStrategy>,
+            Query<&mut Window, With<PrimaryWindow>>,
+        )> = SystemState::new(world);
+
+        let (mut replay, mut game_state, mut time_update_strategy, mut windows) = system_state.get_mut(world);
+        if let Some(recording) = self.recording {
+            replay.recording = recording;
+        }
+        replay.playback_requested = true;
+        replay.seek_to = self.seek_to;
+
+        game_state.track = replay.recording.track;
+        game_state.number_of_players = replay.recording.number_of_players;
+        game_state.time_trial = replay.recording.time_trial;
+        game_state.laps = replay.recording.laps;
+        game_state.kart_class = replay.recording.kart_class;
+        game_state.player_name = replay.recording.player_name.clone().unwrap_or_else(|| game_settings::DEFAULT_PLAYER_NAME.to_string());
+        game_state.player_color = replay.recording.player_color.unwrap_or(game_settings::DEFAULT_PLAYER_COLOR);
+        *game_state = game_state.for_restart();
+
+        if let Some(first) = replay.recording.frames.first() {
+            *time_update_strategy = TimeUpdateStrategy::ManualDuration(first.delta);
+        }
+
+        if self.seek_to.is_some() {
+            for mut window in &mut windows {
+                window.present_mode = PresentMode::AutoNoVsync;
+            }
+        }
+    }
+}
+
+fn handle_playback_input(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut replay: ResMut<Replay>,
+    mut next_ingame_state: ResMut<NextState<IngameState>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    cameras: Query<(Entity, &camera::PlayerCamera, &GlobalTransform, Has<FpsCameraController>)>,
+) {
+    if replay.seek_to.is_some() {
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::C) {
+        for (entity, player_camera, global_transform, is_free) in &cameras {
+            if player_camera.0 != 0 {
+                continue;
+            }
+
+            match is_free {
+                true => {
+                    commands.entity(entity)
+                        .remove::<FpsCameraController>()
+                        .remove::<Smoother>()
+                        .remove::<LookTransform>();
+                },
+                false => {
+                    let eye = global_transform.translation();
+                    commands.entity(entity).insert(FpsCameraBundle::new(
+                        FpsCameraController {
+                            enabled: true,
+                            translate_sensitivity: 20.0,
+                            ..default()
+                        },
+                        eye,
+                        eye + global_transform.forward(),
+                        Vec3::Y,
+                    ));
+                }
+            }
+        }
+    }
+
+    let elapsed = replay.recording.elapsed(replay.current);
+    if keyboard_input.just_pressed(KeyCode::Right) {
+        replay.seek_to = Some(replay.recording.frame_at(elapsed + SEEK_SECONDS));
+        for mut window in &mut windows {
+            window.present_mode = PresentMode::AutoNoVsync;
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Left) {
+        // going backwards means playing the race again from the start
+        commands.add(WatchReplay {
+            recording: None,
+            seek_to: Some(replay.recording.frame_at((elapsed - SEEK_SECONDS).max(0.))),
+        });
+        commands.load_state(AppState::InGame);
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_ingame_state.set(IngameState::EndGame);
+    }
+}
+
+#[derive(Component)]
+struct ReplayTextMarker;
+
+fn spawn_replay_text(
+    mut commands: Commands,
+    game_assets: Res<assets::GameAssets>,
+    text_scaler: ui::text_size::TextScaler,
+) {
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: game_assets.font.clone(),
+                    font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE * 0.6),
+                    color: Color::WHITE,
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Percent(2.),
+                left: Val::Percent(2.),
+                ..default()
+            },
+            ..default()
+        },
+        ReplayTextMarker,
+        CleanupMarker,
+    ));
+}
+
+fn update_replay_text(
+    replay: Res<Replay>,
+    mut texts: Query<&mut Text, With<ReplayTextMarker>>,
+) {
+    let elapsed = replay.recording.elapsed(replay.current) as isize;
+    let total = replay.recording.elapsed(replay.recording.frames.len()) as isize;
+    for mut text in &mut texts {
+        text.sections[0].value = format!(
+            "REPLAY {:02}:{:02} / {:02}:{:02}   Left/Right: seek   C: free camera   Esc: end",
+            elapsed / 60, elapsed % 60, total / 60, total % 60
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::ingame::config::GameConfig;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TowerKind {
+    /// Fires straight at where its owner was when it was placed
+    #[default]
+    Cannon,
+    /// Aims at the nearest enemy kart in range
+    Turret,
+    /// Cuts the acceleration of enemy karts around it
+    SlowField,
+    /// Drops mines on the track in front of it
+    MineDropper,
+    /// Raises and lowers a wall across the owner's lane
+    Barrier,
+}
+
+impl TowerKind {
+    pub const ALL: [TowerKind; 5] = [
+        TowerKind::Cannon,
+        TowerKind::Turret,
+        TowerKind::SlowField,
+        TowerKind::MineDropper,
+        TowerKind::Barrier,
+    ];
+
+    fn index(&self) -> usize {
+        TowerKind::ALL.iter().position(|kind| kind == self).unwrap_or(0)
+    }
+
+    pub fn next(&self) -> Self {
+        TowerKind::ALL[(self.index() + 1) % TowerKind::ALL.len()]
+    }
+
+    pub fn previous(&self) -> Self {
+        TowerKind::ALL[(self.index() + TowerKind::ALL.len() - 1) % TowerKind::ALL.len()]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TowerKind::Cannon => "Cannon",
+            TowerKind::Turret => "Turret",
+            TowerKind::SlowField => "Slow Field",
+            TowerKind::MineDropper => "Mines",
+            TowerKind::Barrier => "Barrier",
+        }
+    }
+
+    pub fn cost(&self, game_config: &GameConfig) -> usize {
+        if cfg!(feature = "endless") {
+            return 0;
+        }
+
+        match self {
+            TowerKind::Cannon => game_config.cannon_cost,
+            TowerKind::Turret => game_config.turret_cost,
+            TowerKind::SlowField => game_config.slow_field_cost,
+            TowerKind::MineDropper => game_config.mine_cost,
+            TowerKind::Barrier => game_config.barrier_cost,
+        }
+    }
+
+    pub fn cooldown(&self, game_config: &GameConfig) -> f32 {
+        match self {
+            TowerKind::Cannon => game_config.tower_action_cooldown,
+            TowerKind::Turret => game_config.turret_cooldown,
+            TowerKind::SlowField => game_config.slow_field_cooldown,
+            TowerKind::MineDropper => game_config.mine_cooldown,
+            TowerKind::Barrier => game_config.barrier_cooldown,
+        }
+    }
+}
+
+/// The tower a player will place next
+#[derive(Component, Default)]
+pub struct SelectedTower(pub TowerKind);
@@ -1,31 +1,43 @@
 use bevy::prelude::*;
 use bevy::ecs::system::{Command, SystemState};
 use bevy::gltf::Gltf;
-use crate::{assets, util, AppState, ingame, };
-use super::{kart, bullet, config, points, common, player, path};
+use crate::{assets, util, AppState, IngameState, ingame, };
+use super::{kart, bullet, collisions, config, points, common, player, path, game_settings, race, items};
 use bevy_turborand::prelude::*;
 use bevy_xpbd_3d::prelude::*;
 use bevy_mod_outline::{OutlineBundle, OutlineVolume, OutlineMode};
 use bevy_kira_audio::prelude::*;
+use std::time::Duration;
 
 #[cfg(feature = "gizmos")]
 use bevy::gizmos::gizmos::Gizmos;
 
+mod kind;
+mod upgrade;
+pub mod weapons;
+
+pub use self::kind::{SelectedTower, TowerKind};
+pub use self::upgrade::TowerUpgrader;
+
 pub struct TowerPlugin;
 impl Plugin for TowerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (tower_actions, handle_launchers).chain().run_if(in_state(AppState::InGame)));
+        app.add_plugins(weapons::WeaponsPlugin)
+            .add_systems(Update, (tower_actions, handle_launchers, handle_tower_deaths).chain().run_if(in_state(AppState::InGame).and_then(not(in_state(IngameState::Paused)))));
     }
 }
 
 #[derive(Component, )]
-struct Tower {
+pub struct Tower {
+    pub kind: TowerKind,
+    pub level: usize,
     delay_start: Timer,
     action_cooldown: Timer,
-    owner: Entity,
+    pub owner: Entity,
     material: Handle<StandardMaterial>,
     target: Vec3,
     color: Color,
+    placed_on_lap: usize,
 }
 
 #[derive(Component)]
@@ -33,45 +45,94 @@ struct Cannon {
     parent: Entity,
 }
 
+/// Solid base of a tower that bullets and karts collide with. It's kept separate
+/// from the tower so it isn't affected by the tower scaling in.
+#[derive(Component)]
+pub struct TowerBody {
+    pub tower: Entity,
+    pub owner: Entity,
+}
+
+/// Towers break apart when they run out of health or have been up for `tower_lifetime_laps`
+fn handle_tower_deaths(
+    mut commands: Commands,
+    mut bullet_hit_event_writer: EventWriter<bullet::CreateHitEvent>,
+    towers: Query<(Entity, &Tower, &Transform, &common::health::Health, &kart::KartColor)>,
+    cannons: Query<(Entity, &Cannon)>,
+    bodies: Query<(Entity, &TowerBody)>,
+    barriers: Query<(Entity, &weapons::Barrier)>,
+    lap_counters: Query<&race::LapCounter>,
+    game_assets: Res<assets::GameAssets>,
+    game_config: Res<config::GameConfig>,
+) {
+    let leading_lap = lap_counters.iter().map(|lap| lap.0).max().unwrap_or(1);
+    let lifetime = game_config.tower_lifetime_laps;
+
+    for (tower_entity, tower, transform, health, kart_color) in &towers {
+        let has_expired = lifetime > 0 && leading_lap >= tower.placed_on_lap + lifetime;
+        if !health.is_dead() && !has_expired {
+            continue;
+        }
+
+        bullet_hit_event_writer.send(bullet::CreateHitEvent {
+            position: transform.translation + Vec3::new(0., game_config.tower_height * 0.5, 0.),
+            count: game_config.kart_die_hit_count,
+            material: game_assets.kart_colors[&kart_color.0].clone_weak(),
+            color: tower.color,
+        });
+
+        let parts = cannons.iter().filter(|(_, cannon)| cannon.parent == tower_entity).map(|(entity, _)| entity)
+            .chain(bodies.iter().filter(|(_, body)| body.tower == tower_entity).map(|(entity, _)| entity))
+            .chain(barriers.iter().filter(|(_, barrier)| barrier.tower == tower_entity).map(|(entity, _)| entity));
+        for entity in parts {
+            commands.entity(entity).despawn_recursive();
+        }
+        commands.entity(tower_entity).despawn_recursive();
+    }
+}
+
 fn test_tower(
     karts: Query<(&Transform, &kart::Kart, &kart::KartColor, &mut points::Points), With<player::Player>>,
     path_manager: Res<path::PathManager>,
     spatial_query: SpatialQuery,
+    game_config: Res<config::GameConfig>,
 
     #[cfg(feature = "gizmos")]
     mut gizmos: Gizmos,
 ) {
+    let track_width = game_config.track_width;
     for (transform, kart, kart_color, mut point) in &karts {
         let starting_height = 5.0;
         let spawn_point = transform.translation;
         let check_point = 
-                path_manager.get_closest_index(spawn_point)
-                            .and_then(|i| path_manager.get_next(i + 2))
-                            .map(|i| path_manager.get(i))
-                            .map(|v| spawn_point.lerp(v, 0.45))
-                            .unwrap_or(spawn_point);
+                if path_manager.is_empty() {
+                    spawn_point
+                } else {
+                    let ahead = path_manager.progress_at(spawn_point) + path_manager.point_spacing() * 3.;
+                    spawn_point.lerp(path_manager.point_at(ahead), 0.45)
+                };
         let rays_to_cast = vec!(
-            check_point + Vec3::new(-config::TRACK_WIDTH, starting_height, 0.0),
-            check_point + Vec3::new(config::TRACK_WIDTH, starting_height, 0.0),
-            check_point + Vec3::new(0.0, starting_height, -config::TRACK_WIDTH),
-            check_point + Vec3::new(0.0, starting_height, config::TRACK_WIDTH),
+            check_point + Vec3::new(-track_width, starting_height, 0.0),
+            check_point + Vec3::new(track_width, starting_height, 0.0),
+            check_point + Vec3::new(0.0, starting_height, -track_width),
+            check_point + Vec3::new(0.0, starting_height, track_width),
 
             // just in case?
-            check_point + Vec3::new(config::TRACK_WIDTH, starting_height, config::TRACK_WIDTH),
-            check_point + Vec3::new(config::TRACK_WIDTH, starting_height, -config::TRACK_WIDTH),
-            check_point + Vec3::new(-config::TRACK_WIDTH, starting_height, -config::TRACK_WIDTH),
-            check_point + Vec3::new(-config::TRACK_WIDTH, starting_height, config::TRACK_WIDTH),
+            check_point + Vec3::new(track_width, starting_height, track_width),
+            check_point + Vec3::new(track_width, starting_height, -track_width),
+            check_point + Vec3::new(-track_width, starting_height, -track_width),
+            check_point + Vec3::new(-track_width, starting_height, track_width),
 
             // uhh ok one more just in case?
-            check_point + Vec3::new(-config::TRACK_WIDTH - 1., starting_height, 0.0),
-            check_point + Vec3::new(config::TRACK_WIDTH + 1., starting_height, 0.0),
-            check_point + Vec3::new(0.0, starting_height, -config::TRACK_WIDTH - 1.),
-            check_point + Vec3::new(0.0, starting_height, config::TRACK_WIDTH + 1.),
-
-            check_point + Vec3::new(config::TRACK_WIDTH + 1., starting_height, config::TRACK_WIDTH + 1.),
-            check_point + Vec3::new(config::TRACK_WIDTH + 1., starting_height, -config::TRACK_WIDTH - 1.),
-            check_point + Vec3::new(-config::TRACK_WIDTH - 1., starting_height, -config::TRACK_WIDTH - 1.),
-            check_point + Vec3::new(-config::TRACK_WIDTH - 1., starting_height, config::TRACK_WIDTH + 1.),
+            check_point + Vec3::new(-track_width - 1., starting_height, 0.0),
+            check_point + Vec3::new(track_width + 1., starting_height, 0.0),
+            check_point + Vec3::new(0.0, starting_height, -track_width - 1.),
+            check_point + Vec3::new(0.0, starting_height, track_width + 1.),
+
+            check_point + Vec3::new(track_width + 1., starting_height, track_width + 1.),
+            check_point + Vec3::new(track_width + 1., starting_height, -track_width - 1.),
+            check_point + Vec3::new(-track_width - 1., starting_height, -track_width - 1.),
+            check_point + Vec3::new(-track_width - 1., starting_height, track_width + 1.),
         );
 
         #[cfg(feature = "gizmos")]
@@ -108,45 +169,64 @@ fn tower_actions(
     cannons: Query<(Entity, &Cannon)>,
     time: Res<Time>,
     spatial_query: SpatialQuery,
+    game_config: Res<config::GameConfig>,
 
     #[cfg(feature = "gizmos")]
     mut gizmos: Gizmos,
 ) {
+    let tower_height = game_config.tower_height;
     for (tower_entity, mut tower, tower_transform, kart_color) in &mut towers {
+        if game_config.is_changed() {
+            let cooldown = tower.cooldown(&game_config);
+            tower.action_cooldown.set_duration(Duration::from_secs_f32(cooldown));
+        }
+
         if !tower.delay_start.tick(time.delta()).finished() {
             continue;
         }
 
         if tower.action_cooldown.tick(time.delta()).just_finished() {
-            let spawn_point = tower_transform.translation + Vec3::new(0., config::TOWER_HEIGHT, 0.);
-            for (cannon_entity, cannon) in &cannons {
-                if cannon.parent == tower_entity {
-                    commands.entity(cannon_entity)
-                        .insert(common::scaler::Scaler::new(Vec3::splat(1.2), 0.1, 0.4, false));
-                }
-            }
+            match tower.kind {
+                TowerKind::Cannon => {
+                    let spawn_point = tower_transform.translation + Vec3::new(0., tower_height, 0.);
+                    for (cannon_entity, cannon) in &cannons {
+                        if cannon.parent == tower_entity {
+                            commands.entity(cannon_entity)
+                                .insert(common::scaler::Scaler::new(Vec3::splat(1.2), 0.1, 0.4, false));
+                        }
+                    }
 
-            commands.add(bullet::BulletSpawner {
-                owner: tower.owner,
-                spawn_point,
-                material: tower.material.clone_weak(),
-                direction: tower.target - spawn_point,
-                color: tower.color,
-                kart_color: *kart_color,
-                speed: 2.0,
-                cleanup_marker: ingame::CleanupMarker,
-            });
+                    for direction in tower.shot_directions(tower.target - spawn_point) {
+                        commands.add(bullet::BulletSpawner {
+                            owner: tower.owner,
+                            spawn_point,
+                            material: tower.material.clone_weak(),
+                            direction,
+                            color: tower.color,
+                            kart_color: *kart_color,
+                            speed: game_config.bullet_speed,
+                            size: tower.bullet_size(),
+                            cleanup_marker: ingame::CleanupMarker,
+                        });
+                    }
+                },
+                TowerKind::Turret => commands.add(weapons::FireTurret { tower: tower_entity }),
+                TowerKind::SlowField => commands.add(weapons::PulseSlowField { tower: tower_entity }),
+                TowerKind::MineDropper => commands.add(weapons::DropMine { tower: tower_entity }),
+                TowerKind::Barrier => commands.add(weapons::ToggleBarrier { tower: tower_entity }),
+            }
         } 
 
         #[cfg(feature = "gizmos")]
         {
-            let bullet_spawn_point = tower_transform.translation + Vec3::new(0., config::TOWER_HEIGHT, 0.);
+            let track_width = game_config.track_width;
+            let bullet_spawn_point = tower_transform.translation + Vec3::new(0., tower_height, 0.);
             gizmos.sphere(bullet_spawn_point, Quat::IDENTITY, 1., Color::RED);
             let rays_to_cast = vec!(
-                tower_transform.translation + Vec3::new(-config::TRACK_WIDTH, 5.0, 0.0),
-                tower_transform.translation + Vec3::new(config::TRACK_WIDTH, 5.0, 0.0),
-                tower_transform.translation + Vec3::new(0.0, 5.0, -config::TRACK_WIDTH),
-                tower_transform.translation + Vec3::new(0.0, 5.0, config::TRACK_WIDTH),
+                tower_transform.translation + Vec3::new(-track_width, 5.0, 0.0),
+                tower_transform.translation + Vec3::new(track_width, 5.0, 0.0),
+                tower_transform.translation + Vec3::new(0.0, 5.0, -track_width),
+                tower_transform.translation + Vec3::new(0.0, 5.0, track_width),
             );
 
             for ray in rays_to_cast {
@@ -168,6 +248,7 @@ fn tower_actions(
 
 pub struct CannonSpawner {
     parent: Entity,
+    kind: TowerKind,
     spawn_point: Vec3,
     target: Vec3,
     outline_color: Color,
@@ -182,20 +263,27 @@ impl Command for CannonSpawner {
             assets::loader::AssetsHandler,
             Res<assets::GameAssets>,
             Res<Assets<Gltf>>,
+            Res<config::GameConfig>,
         )> = SystemState::new(world);
 
-        let (mut assets_handler, game_assets, assets_gltf) = system_state.get_mut(world);
+        let (mut assets_handler, game_assets, assets_gltf, game_config) = system_state.get_mut(world);
+        let tower_height = game_config.tower_height;
 
         let material = assets_handler.materials.add(Color::rgb(0.5, 0.5, 0.5).into()).clone();
-        let mesh = game_assets.cannon.mesh.clone_weak(); 
+        let mesh = match self.kind {
+            TowerKind::Cannon | TowerKind::Turret => game_assets.cannon.mesh.clone_weak(),
+            TowerKind::SlowField => game_assets.slow_field_mesh.clone_weak(),
+            TowerKind::MineDropper => game_assets.mine_mesh.clone_weak(),
+            TowerKind::Barrier => game_assets.barrier_mesh.clone_weak(),
+        };
         world.spawn((
             PbrBundle {
                 mesh,
                 material,
                 transform: 
-                    Transform::from_translation(self.spawn_point + Vec3::new(0., config::TOWER_HEIGHT - 1., 0.))
+                    Transform::from_translation(self.spawn_point + Vec3::new(0., tower_height - 1., 0.))
                             .with_scale(self.scale)
-                            .looking_at(self.target - Vec3::new(0., config::TOWER_HEIGHT - 1., 0.), Vec3::Y),
+                            .looking_at(self.target - Vec3::new(0., tower_height - 1., 0.), Vec3::Y),
                 ..default()
             },
             self.scaler,
@@ -249,39 +337,19 @@ fn handle_launchers(
     }
 }
 
-#[derive(Component)]
-struct TowerAimerMarker;
-pub struct TowerAimerSpawner  {
-    pub entity: Entity,
-    pub material: Handle<StandardMaterial>,
-}
-
-impl Command for TowerAimerSpawner {
-    fn apply(self, world: &mut World) {
-        let mut system_state: SystemState<(
-            assets::loader::AssetsHandler,
-            Res<assets::GameAssets>,
-            Res<Assets<Gltf>>,
-            ResMut<GlobalRng>,
-            Query<(&Transform, &kart::Kart, &kart::KartColor, &mut points::Points)>,
-        )> = SystemState::new(world);
-
-        let (mut assets_handler, game_assets, assets_gltf, mut global_rng, mut points) = system_state.get_mut(world);
-
-        if let Ok((transform, kart, kart_color, mut point)) = points.get_mut(self.entity) {
-            let cost = if cfg!(feature = "endless") { 0 } else { 4 }; 
-            if point.0 >= cost {
-            }
-        }
-    }
-}
-
 pub struct TowerSpawner {
     pub entity: Entity,
     pub material: Handle<StandardMaterial>,
+    pub kind: TowerKind,
 }
 impl Command for TowerSpawner {
     fn apply(self, world: &mut World) {
+        if world.resource::<game_settings::GameState>().time_trial {
+            return;
+        }
+
+        let leading_lap = world.query::<&race::LapCounter>().iter(world).map(|lap| lap.0).max().unwrap_or(1);
+
         let mut system_state: SystemState<(
             assets::loader::AssetsHandler,
             Res<assets::GameAssets>,
@@ -290,14 +358,17 @@ impl Command for TowerSpawner {
             ResMut<GlobalRng>,
             Res<path::PathManager>,
             Res<Audio>,
-            Query<(&Transform, &kart::Kart, &kart::KartColor, &mut points::Points, Has<player::Player>)>,
+            Res<config::GameConfig>,
+            Query<(&Transform, &kart::Kart, &kart::KartColor, &kart::KartClass, &mut points::Points, Has<player::Player>, Has<items::FreeTower>)>,
         )> = SystemState::new(world);
 
-        let (mut assets_handler, game_assets, assets_gltf, spatial_query, mut global_rng, path_manager, audio, mut points) = system_state.get_mut(world);
+        let (mut assets_handler, game_assets, assets_gltf, spatial_query, mut global_rng, path_manager, audio, game_config, mut points) = system_state.get_mut(world);
+        let game_config = game_config.clone();
+        let track_width = game_config.track_width;
 
-        if let Ok((transform, kart, kart_color, mut point, is_player)) = points.get_mut(self.entity) {
+        if let Ok((transform, kart, kart_color, class, mut point, is_player, has_free_tower)) = points.get_mut(self.entity) {
             let spawn_point = transform.translation;
-            let cost = if cfg!(feature = "endless") { 0 } else { 4 }; 
+            let cost = if has_free_tower { 0 } else { class.stats(&game_config).tower_cost(self.kind.cost(&game_config)) };
             if point.0 >= cost {
                 let color = kart.0;
                 let gltf = assets_gltf.get(&game_assets.tower_01);
@@ -306,38 +377,35 @@ impl Command for TowerSpawner {
                     let starting_height = 5.0;
 
                     let check_point = 
-                        if is_player { 
-                            path_manager.get_closest_index(spawn_point)
-                                        .and_then(|i| path_manager.get_next(i + 2))
-                                        .map(|i| path_manager.get(i))
-                                        .map(|v| spawn_point.lerp(v, 0.45))
-                                        .unwrap_or(spawn_point)
+                        if is_player && !path_manager.is_empty() { 
+                            let ahead = path_manager.progress_at(spawn_point) + path_manager.point_spacing() * 3.;
+                            spawn_point.lerp(path_manager.point_at(ahead), 0.45)
                         } else {
                             spawn_point
                         };
 
                     let rays_to_cast = vec!(
-                        check_point + Vec3::new(-config::TRACK_WIDTH, starting_height, 0.0),
-                        check_point + Vec3::new(config::TRACK_WIDTH, starting_height, 0.0),
-                        check_point + Vec3::new(0.0, starting_height, -config::TRACK_WIDTH),
-                        check_point + Vec3::new(0.0, starting_height, config::TRACK_WIDTH),
+                        check_point + Vec3::new(-track_width, starting_height, 0.0),
+                        check_point + Vec3::new(track_width, starting_height, 0.0),
+                        check_point + Vec3::new(0.0, starting_height, -track_width),
+                        check_point + Vec3::new(0.0, starting_height, track_width),
 
                         // just in case?
-                        check_point + Vec3::new(config::TRACK_WIDTH, starting_height, config::TRACK_WIDTH),
-                        check_point + Vec3::new(config::TRACK_WIDTH, starting_height, -config::TRACK_WIDTH),
-                        check_point + Vec3::new(-config::TRACK_WIDTH, starting_height, -config::TRACK_WIDTH),
-                        check_point + Vec3::new(-config::TRACK_WIDTH, starting_height, config::TRACK_WIDTH),
+                        check_point + Vec3::new(track_width, starting_height, track_width),
+                        check_point + Vec3::new(track_width, starting_height, -track_width),
+                        check_point + Vec3::new(-track_width, starting_height, -track_width),
+                        check_point + Vec3::new(-track_width, starting_height, track_width),
 
                         // uhh ok one more just in case?
-                        check_point + Vec3::new(-config::TRACK_WIDTH - 1., starting_height, 0.0),
-                        check_point + Vec3::new(config::TRACK_WIDTH + 1., starting_height, 0.0),
-                        check_point + Vec3::new(0.0, starting_height, -config::TRACK_WIDTH - 1.),
-                        check_point + Vec3::new(0.0, starting_height, config::TRACK_WIDTH + 1.),
-
-                        check_point + Vec3::new(config::TRACK_WIDTH + 1., starting_height, config::TRACK_WIDTH + 1.),
-                        check_point + Vec3::new(config::TRACK_WIDTH + 1., starting_height, -config::TRACK_WIDTH - 1.),
-                        check_point + Vec3::new(-config::TRACK_WIDTH - 1., starting_height, -config::TRACK_WIDTH - 1.),
-                        check_point + Vec3::new(-config::TRACK_WIDTH - 1., starting_height, config::TRACK_WIDTH + 1.),
+                        check_point + Vec3::new(-track_width - 1., starting_height, 0.0),
+                        check_point + Vec3::new(track_width + 1., starting_height, 0.0),
+                        check_point + Vec3::new(0.0, starting_height, -track_width - 1.),
+                        check_point + Vec3::new(0.0, starting_height, track_width + 1.),
+
+                        check_point + Vec3::new(track_width + 1., starting_height, track_width + 1.),
+                        check_point + Vec3::new(track_width + 1., starting_height, -track_width - 1.),
+                        check_point + Vec3::new(-track_width - 1., starting_height, -track_width - 1.),
+                        check_point + Vec3::new(-track_width - 1., starting_height, track_width + 1.),
                     );
 
                     for ray in rays_to_cast {
@@ -352,7 +420,7 @@ impl Command for TowerSpawner {
                         if hit.is_none() {
                             let original_offset = ray - check_point;
                             let normalized_offset= (original_offset - Vec3::new(0., starting_height, 0.)).normalize();
-                            let buffered_position = config::TRACK_WIDTH + config::TOWER_POSITION_BUFFER;
+                            let buffered_position = track_width + game_config.tower_position_buffer;
                             let offset_with_buffer = normalized_offset * Vec3::new(buffered_position, 0., buffered_position);
                             let target = spawn_point;
                             let spawn_point = check_point + offset_with_buffer;
@@ -372,7 +440,6 @@ impl Command for TowerSpawner {
                             world.spawn((
                                 kart_color,
                                 ingame::CleanupMarker,
-                                TowerAimerMarker,
                                 LaunchedTower {
                                     initial: initial_translation,
                                     target: spawn_point,
@@ -423,12 +490,15 @@ impl Command for TowerSpawner {
 
                             let tower_id = world.spawn((
                                 Tower {
+                                    kind: self.kind,
+                                    level: 1,
                                     target,
                                     owner: self.entity,
                                     material: self.material,
                                     color,
+                                    placed_on_lap: leading_lap,
                                     delay_start: Timer::from_seconds(random, TimerMode::Once),
-                                    action_cooldown:Timer::from_seconds(0.5, TimerMode::Repeating), 
+                                    action_cooldown:Timer::from_seconds(self.kind.cooldown(&game_config), TimerMode::Repeating), 
                                 },
                                 kart_color,
                                 AudioEmitter {
@@ -461,6 +531,7 @@ impl Command for TowerSpawner {
 
                             let cannon_spawner = CannonSpawner {
                                 parent: tower_id,
+                                kind: self.kind,
                                 spawn_point,
                                 outline_color: if is_player { tower_color } else { Color::BLACK },
                                 outline_width: if is_player { 8.0 } else { 1.0 },
@@ -469,6 +540,33 @@ impl Command for TowerSpawner {
                                 scaler,
                             };
                             cannon_spawner.apply(world);
+
+                            let tower_height = game_config.tower_height;
+                            world.spawn((
+                                SpatialBundle::from_transform(Transform::from_translation(spawn_point + Vec3::new(0., tower_height * 0.5, 0.))),
+                                RigidBody::Static,
+                                Collider::cylinder(tower_height, 1.5),
+                                CollisionLayers::new([collisions::Layer::Ground], [collisions::Layer::Kart, collisions::Layer::Bullet]),
+                                TowerBody {
+                                    tower: tower_id,
+                                    owner: self.entity,
+                                },
+                                ingame::CleanupMarker,
+                            ));
+
+                            common::health::HealthBarSpawner {
+                                health_points: game_config.tower_health,
+                                parent: tower_id,
+                                cleanup_marker: ingame::CleanupMarker,
+                                offset: Vec3::new(0., tower_height + 2., 0.),
+                            }.apply(world);
+
+                            world.entity_mut(self.entity).remove::<items::FreeTower>();
+                            world.send_event(race::events::TowerPlaced {
+                                tower: tower_id,
+                                owner: self.entity,
+                                kind: self.kind,
+                            });
                             break;
                         } 
                     }
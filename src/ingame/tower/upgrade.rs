@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+use bevy::ecs::system::{Command, SystemState};
+use std::time::Duration;
+use crate::{ingame, ui::follow_text};
+use crate::ingame::{config, kart, points};
+use super::{Cannon, Tower};
+
+/// Angle between each bullet of a multi-shot
+const SHOT_SPREAD: f32 = 0.12;
+const COOLDOWN_PER_LEVEL: f32 = 0.8;
+const BULLET_SIZE_PER_LEVEL: f32 = 0.25;
+const RANGE_PER_LEVEL: f32 = 0.25;
+/// The cannon goes from grey to bronze to gold as it levels up
+const LEVEL_COLORS: [Color; 3] = [
+    Color::rgb(0.5, 0.5, 0.5),
+    Color::rgb(0.8, 0.5, 0.2),
+    Color::rgb(1.0, 0.84, 0.0),
+];
+
+impl Tower {
+    pub fn cooldown(&self, game_config: &config::GameConfig) -> f32 {
+        self.kind.cooldown(game_config) * COOLDOWN_PER_LEVEL.powi(self.level as i32 - 1)
+    }
+
+    pub fn bullet_size(&self) -> f32 {
+        1. + BULLET_SIZE_PER_LEVEL * (self.level - 1) as f32
+    }
+
+    pub fn range_multiplier(&self) -> f32 {
+        1. + RANGE_PER_LEVEL * (self.level - 1) as f32
+    }
+
+    /// Directions to fire in, one bullet per level fanned out around `direction`
+    pub fn shot_directions(&self, direction: Vec3) -> Vec<Vec3> {
+        let middle = (self.level - 1) as f32 * 0.5;
+        (0..self.level)
+            .map(|shot| Quat::from_rotation_y((shot as f32 - middle) * SHOT_SPREAD) * direction)
+            .collect()
+    }
+
+    pub fn upgrade_cost(&self, game_config: &config::GameConfig) -> usize {
+        if cfg!(feature = "endless") { 0 } else { game_config.tower_upgrade_cost * self.level }
+    }
+}
+
+/// Levels up the closest tower the kart owns if it's in range and the kart can afford it
+pub struct TowerUpgrader {
+    pub entity: Entity,
+}
+impl Command for TowerUpgrader {
+    fn apply(self, world: &mut World) {
+        let mut system_state: SystemState<(
+            Query<(&Transform, &mut points::Points), With<kart::Kart>>,
+            Query<(Entity, &mut Tower, &Transform), Without<kart::Kart>>,
+            Query<(Entity, &Cannon, &Handle<StandardMaterial>)>,
+            Query<(&follow_text::FollowText, &mut Text)>,
+            ResMut<Assets<StandardMaterial>>,
+            Res<config::GameConfig>,
+        )> = SystemState::new(world);
+
+        let (mut karts, mut towers, cannons, mut labels, mut materials, game_config) = system_state.get_mut(world);
+        let Ok((kart_transform, mut points)) = karts.get_mut(self.entity) else { return };
+        let kart_position = kart_transform.translation * Vec3::new(1., 0., 1.);
+
+        let closest = towers.iter_mut()
+                            .filter(|(_, tower, _)| tower.owner == self.entity && tower.level < game_config.tower_max_level)
+                            .map(|(entity, tower, transform)| ((transform.translation * Vec3::new(1., 0., 1.)).distance(kart_position), entity, tower))
+                            .filter(|(distance, _, _)| *distance < game_config.tower_upgrade_range)
+                            .min_by(|a, b| a.0.total_cmp(&b.0));
+        let Some((_, tower_entity, mut tower)) = closest else { return };
+
+        let cost = tower.upgrade_cost(&game_config);
+        if points.0 < cost {
+            return;
+        }
+
+        points.0 -= cost;
+        tower.level += 1;
+        let cooldown = tower.cooldown(&game_config);
+        tower.action_cooldown.set_duration(Duration::from_secs_f32(cooldown));
+        let level = tower.level;
+
+        let level_color = LEVEL_COLORS[(level - 1).min(LEVEL_COLORS.len() - 1)];
+        let mut cannon_entities = vec![];
+        for (cannon_entity, cannon, material) in &cannons {
+            if cannon.parent == tower_entity {
+                if let Some(material) = materials.get_mut(material) {
+                    material.base_color = level_color;
+                }
+                cannon_entities.push(cannon_entity);
+            }
+        }
+
+        let label = format!("Lv {}", level);
+        let mut has_label = false;
+        for (follow_text, mut text) in &mut labels {
+            if cannon_entities.contains(&follow_text.following) {
+                text.sections[0].value = label.clone();
+                has_label = true;
+            }
+        }
+
+        if !has_label {
+            for cannon_entity in cannon_entities {
+                follow_text::SpawnFollowText {
+                    follow_entity: cannon_entity,
+                    text: label.clone(),
+                    color: level_color,
+                    cleanup_marker: ingame::CleanupMarker,
+                }.apply(world);
+            }
+        }
+    }
+}
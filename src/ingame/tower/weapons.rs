@@ -0,0 +1,304 @@
+use bevy::prelude::*;
+use bevy::ecs::system::{Command, SystemState};
+use bevy_turborand::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+use bevy_mod_outline::{OutlineBundle, OutlineVolume, OutlineMode};
+use crate::{assets, ingame, AppState};
+use crate::ingame::{bullet, collisions, common, config, controller, kart};
+use super::{Cannon, Tower};
+
+const BARRIER_HEIGHT: f32 = 3.0;
+const MINE_SPREAD: f32 = 3.0;
+
+pub struct WeaponsPlugin;
+impl Plugin for WeaponsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_slowed.run_if(in_state(AppState::InGame)));
+    }
+}
+
+/// Shoots at the closest kart in range that isn't the tower's owner
+pub struct FireTurret {
+    pub tower: Entity,
+}
+impl Command for FireTurret {
+    fn apply(self, world: &mut World) {
+        let mut system_state: SystemState<(
+            Query<(&Tower, &Transform, &kart::KartColor)>,
+            Query<(Entity, &Transform), With<kart::Kart>>,
+            Query<(Entity, &Cannon, &mut Transform), (Without<Tower>, Without<kart::Kart>)>,
+            Res<config::GameConfig>,
+        )> = SystemState::new(world);
+
+        let (towers, karts, mut cannons, game_config) = system_state.get_mut(world);
+        let Ok((tower, tower_transform, kart_color)) = towers.get(self.tower) else { return };
+        let tower_height = game_config.tower_height;
+        let spawn_point = tower_transform.translation + Vec3::new(0., tower_height, 0.);
+        let target = karts.iter()
+                          .filter(|(entity, _)| *entity != tower.owner)
+                          .map(|(_, transform)| transform.translation)
+                          .filter(|translation| translation.distance(spawn_point) < game_config.turret_range * tower.range_multiplier())
+                          .min_by(|a, b| a.distance(spawn_point).total_cmp(&b.distance(spawn_point)));
+        let Some(target) = target else { return };
+
+        let mut recoiling_cannons = vec![];
+        for (cannon_entity, cannon, mut transform) in &mut cannons {
+            if cannon.parent == self.tower {
+                transform.look_at(target - Vec3::new(0., tower_height - 1., 0.), Vec3::Y);
+                recoiling_cannons.push(cannon_entity);
+            }
+        }
+
+        let bullet_spawners = tower.shot_directions(target - spawn_point)
+            .into_iter()
+            .map(|direction| bullet::BulletSpawner {
+                owner: tower.owner,
+                spawn_point,
+                material: tower.material.clone_weak(),
+                direction,
+                color: tower.color,
+                kart_color: *kart_color,
+                speed: game_config.bullet_speed,
+                size: tower.bullet_size(),
+                cleanup_marker: ingame::CleanupMarker,
+            })
+            .collect::<Vec<_>>();
+
+        for cannon_entity in recoiling_cannons {
+            world.entity_mut(cannon_entity)
+                 .insert(common::scaler::Scaler::new(Vec3::splat(1.2), 0.1, 0.4, false));
+        }
+        for bullet_spawner in bullet_spawners {
+            bullet_spawner.apply(world);
+        }
+    }
+}
+
+/// Karts inside a slow field accelerate at a fraction of the normal rate
+#[derive(Component)]
+pub struct Slowed(Timer);
+
+pub struct PulseSlowField {
+    pub tower: Entity,
+}
+impl Command for PulseSlowField {
+    fn apply(self, world: &mut World) {
+        let mut system_state: SystemState<(
+            Query<(&Tower, &Transform)>,
+            Query<(Entity, &Transform), With<kart::Kart>>,
+            Res<config::GameConfig>,
+        )> = SystemState::new(world);
+
+        let (towers, karts, game_config) = system_state.get_mut(world);
+        let Ok((tower, tower_transform)) = towers.get(self.tower) else { return };
+        let center = tower_transform.translation * Vec3::new(1., 0., 1.);
+        let radius = game_config.slow_field_radius * tower.range_multiplier();
+        // lasts a little longer than a pulse so karts inside stay slowed
+        let duration = game_config.slow_field_cooldown * 2.;
+
+        let slowed = karts.iter()
+                          .filter(|(entity, transform)| *entity != tower.owner && (transform.translation * Vec3::new(1., 0., 1.)).distance(center) < radius)
+                          .map(|(entity, _)| entity)
+                          .collect::<Vec<_>>();
+
+        for entity in slowed {
+            world.entity_mut(entity).insert(Slowed(Timer::from_seconds(duration, TimerMode::Once)));
+        }
+    }
+}
+
+fn update_slowed(
+    mut commands: Commands,
+    mut slowed_karts: Query<(Entity, &mut Slowed, &mut controller::MovementAcceleration, Option<&kart::KartClass>)>,
+    game_config: Res<config::GameConfig>,
+    time: Res<Time>,
+) {
+    for (entity, mut slowed, mut acceleration, class) in &mut slowed_karts {
+        let class_acceleration = game_config.base_acceleration * class.copied().unwrap_or_default().stats(&game_config).acceleration;
+        if slowed.0.tick(time.delta()).finished() {
+            acceleration.0 = class_acceleration;
+            commands.entity(entity).remove::<Slowed>();
+        } else {
+            acceleration.0 = class_acceleration * game_config.slow_field_factor;
+        }
+    }
+}
+
+/// Hurts the first enemy kart to drive over it
+#[derive(Component)]
+pub struct Mine {
+    pub owner: Entity,
+    pub tower: Entity,
+    pub color: Color,
+}
+
+pub struct DropMine {
+    pub tower: Entity,
+}
+impl Command for DropMine {
+    fn apply(self, world: &mut World) {
+        let mut system_state: SystemState<(
+            Query<(&Tower, &kart::KartColor)>,
+            Query<&Mine>,
+            ResMut<GlobalRng>,
+            Res<assets::GameAssets>,
+            Res<config::GameConfig>,
+        )> = SystemState::new(world);
+
+        let (towers, mines, mut global_rng, game_assets, game_config) = system_state.get_mut(world);
+        let Ok((tower, kart_color)) = towers.get(self.tower) else { return };
+        if mines.iter().filter(|mine| mine.tower == self.tower).count() >= game_config.mine_limit + tower.level - 1 {
+            return;
+        }
+
+        let offset = Vec3::new(global_rng.f32_normalized(), 0., global_rng.f32_normalized()) * MINE_SPREAD;
+        let mine = Mine {
+            owner: tower.owner,
+            tower: self.tower,
+            color: tower.color,
+        };
+        let mesh = game_assets.mine_mesh.clone_weak();
+        let material = game_assets.kart_colors[&kart_color.0].clone_weak();
+        let translation = tower.target + offset;
+
+        world.spawn((
+            PbrBundle {
+                mesh,
+                material,
+                transform: Transform::from_translation(translation),
+                ..default()
+            },
+            OutlineBundle {
+                outline: OutlineVolume {
+                    visible: true,
+                    width: 1.0,
+                    colour: Color::BLACK,
+                },
+                mode: OutlineMode::RealVertex,
+                ..default()
+            },
+            Collider::ball(0.8),
+            Sensor,
+            mine,
+            ingame::CleanupMarker,
+        ));
+    }
+}
+
+/// A wall from the tower to where its owner was when it was placed
+#[derive(Component)]
+pub struct Barrier {
+    pub tower: Entity,
+    owner: Entity,
+    raised: bool,
+    length: f32,
+}
+
+impl Barrier {
+    /// The owner drives through its own barrier, everyone else is stopped by it
+    pub fn blocks(&self, kart: Entity) -> bool {
+        self.raised && self.owner != kart
+    }
+}
+
+pub struct ToggleBarrier {
+    pub tower: Entity,
+}
+impl Command for ToggleBarrier {
+    fn apply(self, world: &mut World) {
+        let mut system_state: SystemState<(
+            Query<(&Tower, &Transform)>,
+            Query<(Entity, &mut Barrier, &mut Visibility)>,
+            ResMut<Assets<Mesh>>,
+        )> = SystemState::new(world);
+
+        let (towers, mut barriers, mut meshes) = system_state.get_mut(world);
+        let Ok((tower, tower_transform)) = towers.get(self.tower) else { return };
+        let end = tower.target;
+        let start = Vec3::new(tower_transform.translation.x, end.y, tower_transform.translation.z);
+        let material = tower.material.clone_weak();
+        let owner = tower.owner;
+
+        let existing = barriers.iter_mut()
+                               .find(|(_, barrier, _)| barrier.tower == self.tower)
+                               .map(|(entity, mut barrier, mut visibility)| {
+                                   barrier.raised = !barrier.raised;
+                                   *visibility = if barrier.raised { Visibility::Inherited } else { Visibility::Hidden };
+                                   (entity, barrier.raised, barrier.length)
+                               });
+        if let Some((entity, raised, length)) = existing {
+            if raised {
+                world.entity_mut(entity).insert(Collider::cuboid(1.0, BARRIER_HEIGHT, length));
+            } else {
+                world.entity_mut(entity).remove::<Collider>();
+            }
+            return;
+        }
+
+        let length = start.distance(end).max(1.);
+        let center = start.lerp(end, 0.5) + Vec3::new(0., BARRIER_HEIGHT * 0.5 - 0.5, 0.);
+        let mesh = meshes.add(shape::Box::new(1.0, BARRIER_HEIGHT, length).into());
+
+        world.spawn((
+            PbrBundle {
+                mesh,
+                material,
+                transform: Transform::from_translation(center).looking_at(Vec3::new(end.x, center.y, end.z), Vec3::Y),
+                ..default()
+            },
+            OutlineBundle {
+                outline: OutlineVolume {
+                    visible: true,
+                    width: 1.0,
+                    colour: Color::BLACK,
+                },
+                mode: OutlineMode::RealVertex,
+                ..default()
+            },
+            RigidBody::Static,
+            Collider::cuboid(1.0, BARRIER_HEIGHT, length),
+            CollisionLayers::new([collisions::Layer::Ground], [collisions::Layer::Kart]),
+            Barrier {
+                tower: self.tower,
+                owner,
+                raised: true,
+                length,
+            },
+            ingame::CleanupMarker,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn barrier(owner: Entity, raised: bool) -> Barrier {
+        Barrier {
+            tower: Entity::from_raw(0),
+            owner,
+            raised,
+            length: 1.,
+        }
+    }
+
+    #[test]
+    fn barrier_does_not_block_its_owner() {
+        let owner = Entity::from_raw(1);
+        assert!(!barrier(owner, true).blocks(owner));
+    }
+
+    #[test]
+    fn barrier_blocks_other_karts() {
+        let owner = Entity::from_raw(1);
+        let other = Entity::from_raw(2);
+        assert!(barrier(owner, true).blocks(other));
+    }
+
+    #[test]
+    fn lowered_barrier_blocks_nobody() {
+        let owner = Entity::from_raw(1);
+        let other = Entity::from_raw(2);
+        assert!(!barrier(owner, false).blocks(other));
+    }
+}
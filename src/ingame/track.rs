@@ -0,0 +1,38 @@
+/// A track that can be picked from the track select menu. The glb is
+/// interpreted by the scene hook in `ingame::setup` so any track authored with
+/// the same node names (`collide`, `path`, `waypoint`, `kart_spawner`, ...)
+/// can be added here.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Track {
+    pub name: &'static str,
+    pub path: &'static str,
+    /// Laps needed to win, or `None` to race until a single kart is left
+    pub laps: Option<usize>,
+    pub music: &'static str,
+}
+
+impl Track {
+    /// Laps driven in a time trial, tracks without a lap target use three
+    pub fn time_trial_laps(&self) -> usize {
+        self.laps.unwrap_or(3)
+    }
+}
+
+pub const TRACKS: [Track; 2] = [
+    Track {
+        name: "Tower Loop",
+        path: "models/track.glb",
+        laps: None,
+        music: "audio/bgm.ogg",
+    },
+    Track {
+        name: "Mirror Loop",
+        path: "models/track_mirror.glb",
+        laps: Some(3),
+        music: "audio/title.ogg",
+    },
+];
+
+pub fn get(index: usize) -> Track {
+    TRACKS[index % TRACKS.len()]
+}
@@ -1,5 +1,5 @@
 use bevy::prelude::*;
-use crate::{assets::GameAssets, cleanup, ui, IngameState, ingame::{player, race, kart, points, game_settings}, AppState, util::audio};
+use crate::{assets::GameAssets, cleanup, ui, IngameState, ingame::{player, race, race::time_trial, kart, points, game_settings, replay, track}, AppState, util::audio};
 use crate::assets::command_ext::*;
 use std::time::Duration;
 
@@ -38,6 +38,14 @@ fn handle_input(
             *cooldown = 0.;
             commands.load_state(AppState::Splash);
         }
+
+        let watch_replay = keyboard_input.just_pressed(KeyCode::R) ||
+            gamepads.iter().any(|gamepad| buttons.just_pressed(GamepadButton { gamepad,  button_type: GamepadButtonType::North }));
+        if watch_replay {
+            *cooldown = 0.;
+            commands.add(replay::WatchReplay { recording: None, seek_to: None });
+            commands.load_state(AppState::InGame);
+        }
     }
 }
 
@@ -49,7 +57,10 @@ fn setup(
     text_scaler: ui::text_size::TextScaler,
     game_state: Res<game_settings::GameState>,
     mut audio: audio::GameAudio,
-    karts: Query<Entity, With<kart::Kart>>,
+    racing: Query<(&kart::Kart, &kart::KartName, &kart::KartClass, Option<&player::Player>, &race::LapCounter, Option<&race::placement::Place>), Without<race::Finished>>,
+    race_results: Res<race::RaceResults>,
+    lap_timers: Query<&time_trial::LapTimer, With<player::Player>>,
+    best_laps: Res<time_trial::BestLaps>,
 ) {
     let root_node = 
     commands
@@ -104,6 +115,7 @@ fn setup(
                                   game_settings::GameEndingState::Winner => "You Won!",
                                   game_settings::GameEndingState::Died => "Knocked Out!",
                                   game_settings::GameEndingState::FellBehind => "Fell Behind!",
+                                  game_settings::GameEndingState::Finished => "Race Over!",
                                   _ => "Hey uh.. what happened?? <_<"
                               },
                         TextStyle {
@@ -133,7 +145,6 @@ fn setup(
             ..default()
         },))
         .with_children(|builder| {
-            let number_of_racers = karts.iter().len() + if game_state.ending_state != game_settings::GameEndingState::Winner { 1 } else { 0 };
             builder.spawn((
                 TextBundle {
                     text: Text::from_section(
@@ -148,24 +159,82 @@ fn setup(
                 },
             ));
 
-            builder.spawn((
-                TextBundle {
-                    text: Text::from_section(
-                        format!("Place: {} / {}", game_state.player_place, number_of_racers),
-                        TextStyle {
-                            font: game_assets.font.clone(),
-                            font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE),
-                            color: Color::BLACK,
-                        },
-                    ),
+            let mut still_racing = racing.iter()
+                                         .map(|(kart, name, class, player, lap_counter, place)| (place.map_or(usize::MAX, |place| place.0), kart.0, player.map(|player| player.0), name.0.clone(), *class, lap_counter.0))
+                                         .collect::<Vec<_>>();
+            still_racing.sort_by_key(|(place, ..)| *place);
+            let standings = race_results.standings(still_racing.into_iter().map(|(_, color, player, name, class, lap)| (color, player, name, class, lap)));
+
+            for (i, result) in standings.iter().enumerate() {
+                let outcome = match result.outcome {
+                    race::RaceOutcome::Finished(time) => time_trial::format_time(time),
+                    race::RaceOutcome::Unfinished(lap) => format!("Lap {}", lap),
+                    race::RaceOutcome::KnockedOut => "Knocked Out".to_string(),
+                };
+
+                builder.spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(70.0),
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::SpaceBetween,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
                     ..default()
-                },
-            ));
+                })
+                .with_children(|row| {
+                    row.spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(text_scaler.scale(ui::DEFAULT_FONT_SIZE * 0.5)),
+                            height: Val::Px(text_scaler.scale(ui::DEFAULT_FONT_SIZE * 0.5)),
+                            ..default()
+                        },
+                        background_color: BackgroundColor(result.color),
+                        ..default()
+                    });
+
+                    for value in [format!("{}.", i + 1), result.name.clone(), result.class.label().to_string(), outcome] {
+                        row.spawn(TextBundle {
+                            text: Text::from_section(
+                                value,
+                                TextStyle {
+                                    font: game_assets.font.clone(),
+                                    font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE * 0.6),
+                                    color: if result.player.is_some() { Color::BLACK } else { Color::DARK_GRAY },
+                                },
+                            ),
+                            ..default()
+                        });
+                    }
+                });
+            }
+
+            if game_state.time_trial {
+                let fastest_lap = lap_timers.iter().filter_map(|lap_timer| lap_timer.fastest_lap()).next();
+                let best_lap = best_laps.get(track::get(game_state.track));
+                builder.spawn((
+                    TextBundle {
+                        text: Text::from_section(
+                            format!(
+                                "Fastest Lap: {}  Best: {}",
+                                fastest_lap.map(|lap| time_trial::format_time(lap.time)).unwrap_or("-".to_string()),
+                                best_lap.map(|best| time_trial::format_time(best.lap.time)).unwrap_or("-".to_string()),
+                            ),
+                            TextStyle {
+                                font: game_assets.font.clone(),
+                                font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE),
+                                color: Color::BLACK,
+                            },
+                        ),
+                        ..default()
+                    },
+                ));
+            }
 
             builder.spawn((
                 TextBundle {
                     text: Text::from_section(
-                        format!("Laps: {}", game_state.player_lap),
+                        format!("Peak Entity Count: {}", game_state.peak_number_of_entities),
                         TextStyle {
                             font: game_assets.font.clone(),
                             font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE),
@@ -179,10 +248,10 @@ fn setup(
             builder.spawn((
                 TextBundle {
                     text: Text::from_section(
-                        format!("Peak Entity Count: {}", game_state.peak_number_of_entities),
+                        "Press R to watch the replay",
                         TextStyle {
                             font: game_assets.font.clone(),
-                            font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE),
+                            font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE * 0.6),
                             color: Color::BLACK,
                         },
                     ),
@@ -1,4 +1,4 @@
-use crate::{assets::GameAssets, cleanup, ui, IngameState, ingame::{player, race, kart, points}};
+use crate::{assets::GameAssets, cleanup, ui, IngameState, ingame::{player, race, race::time_trial, kart, items, points, camera, config, game_settings, tower, track}};
 use bevy::prelude::*;
 use std::collections::HashMap;
 
@@ -9,14 +9,19 @@ const UI_UPDATE: f64 = 0.5;
 pub struct InGameUIPlugin;
 impl Plugin for InGameUIPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(IngameState::InGame), setup)
+        // the HUD stays up under the pause menu so it's only built and torn down when racing starts or stops
+        app.add_systems(OnEnter(IngameState::InGame), setup.run_if(not(any_with_component::<CleanupMarker>())))
         .insert_resource(Time::from_seconds(UI_UPDATE))
         .add_plugins((end_game::EndGamePlugin, pre_game::PreGamePlugin))
         .add_systems(
             FixedUpdate,
-            (update_lap_counter, update_place, update_credits).run_if(in_state(IngameState::InGame)),
+            (update_lap_counter, (update_place, update_credits, update_held_item).run_if(not(time_trial::is_time_trial))).run_if(in_state(IngameState::InGame)),
         )
-        .add_systems(OnExit(IngameState::InGame), cleanup::<CleanupMarker>);
+        .add_systems(Update, update_lap_time.run_if(in_state(IngameState::InGame).and_then(time_trial::is_time_trial)))
+        .add_systems(Update, flash_place.run_if(in_state(IngameState::InGame).and_then(not(time_trial::is_time_trial))))
+        .add_systems(Update, update_warning.run_if(in_state(IngameState::InGame)))
+        .add_systems(OnExit(IngameState::InGame), cleanup::<CleanupMarker>.run_if(not(in_state(IngameState::Paused))))
+        .add_systems(OnExit(IngameState::Paused), cleanup::<CleanupMarker>.run_if(not(in_state(IngameState::InGame))));
     }
 }
 
@@ -24,13 +29,30 @@ impl Plugin for InGameUIPlugin {
 struct CleanupMarker;
 
 #[derive(Component)]
-struct LapMarker;
+struct LapMarker(usize);
 
 #[derive(Component)]
-struct PlaceMarker;
+struct PlaceMarker(usize);
 
+/// Tints the place text for a moment after the player gains or loses a place
 #[derive(Component)]
-struct CreditsMarker;
+struct PlaceFlash(Timer);
+
+#[derive(Component)]
+struct CreditsMarker(usize);
+
+#[derive(Component)]
+struct ItemMarker(usize);
+
+#[derive(Component)]
+struct LapTimeMarker(usize);
+
+/// Big text in the middle of the HUD for driving the wrong way or a lap that didn't count
+#[derive(Component)]
+struct WarningMarker {
+    player: usize,
+    lap_rejected: Timer,
+}
 
 fn setup(
     mut commands: Commands,
@@ -38,13 +60,31 @@ fn setup(
     mut images: ResMut<Assets<Image>>,
     window_size: Res<ui::text_size::WindowSize>,
     text_scaler: ui::text_size::TextScaler,
+    game_state: Res<game_settings::GameState>,
 ) {
+    for player in 0..game_state.number_of_players {
+        spawn_player_hud(&mut commands, &game_assets, &text_scaler, player, game_state.number_of_players, game_state.time_trial);
+    }
+}
+
+fn spawn_player_hud(
+    commands: &mut Commands,
+    game_assets: &GameAssets,
+    text_scaler: &ui::text_size::TextScaler,
+    player: usize,
+    number_of_players: usize,
+    time_trial: bool,
+) {
+    // each HUD covers the same section of the window as its player's camera
+    let viewport = camera::viewport_rect(player, number_of_players);
     let root_node = 
     commands
         .spawn(NodeBundle {
             style: Style {
-                width: Val::Percent(100.0),
-                height: Val::Percent(100.0),
+                left: Val::Percent(viewport.min.x * 100.0),
+                top: Val::Percent(viewport.min.y * 100.0),
+                width: Val::Percent(viewport.width() * 100.0),
+                height: Val::Percent(viewport.height() * 100.0),
                 position_type: PositionType::Absolute,
                 justify_content: JustifyContent::Center,
                 align_items: AlignItems::FlexStart,
@@ -128,7 +168,7 @@ fn setup(
                 ),
                 ..default()
             },
-            LapMarker,
+            LapMarker(player),
         )).id();
 
     let place_node= 
@@ -159,7 +199,7 @@ fn setup(
                 ),
                 ..default()
             },
-            PlaceMarker, // haha
+            PlaceMarker(player), // haha
         )).id();
 
     let credits_node= 
@@ -190,7 +230,7 @@ fn setup(
                 ),
                 ..default()
             },
-            CreditsMarker,
+            CreditsMarker(player),
         )).id();
 
     commands.entity(credits_node).add_child(credits);
@@ -201,42 +241,212 @@ fn setup(
     commands.entity(lap_counter_node).add_child(lap_counter);
     commands.entity(top_row_left_side).add_child(lap_counter_node);
 
+    if !time_trial {
+        let held_item =
+            commands.spawn((
+                TextBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font: game_assets.font.clone(),
+                            font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE * 0.8),
+                            color: Color::BLACK,
+                        },
+                    ),
+                    ..default()
+                },
+                ItemMarker(player),
+            )).id();
+        commands.entity(top_row_right_side).add_child(held_item);
+    }
+
+    if time_trial {
+        let lap_time =
+            commands.spawn((
+                TextBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font: game_assets.font.clone(),
+                            font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE * 0.8),
+                            color: Color::BLACK,
+                        },
+                    ),
+                    ..default()
+                },
+                LapTimeMarker(player),
+            )).id();
+        commands.entity(top_row_right_side).add_child(lap_time);
+    }
+
     commands.entity(top_row).add_child(top_row_left_side);
     commands.entity(top_row).add_child(top_row_right_side);
 
     commands.entity(root_node).add_child(top_row);
+
+    let warning =
+        commands.spawn((
+            TextBundle {
+                text: Text::from_section(
+                    "",
+                    TextStyle {
+                        font: game_assets.font.clone(),
+                        font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE * 1.5),
+                        color: Color::RED,
+                    },
+                ),
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Percent(30.0),
+                    ..default()
+                },
+                ..default()
+            },
+            WarningMarker {
+                player,
+                lap_rejected: Timer::from_seconds(0., TimerMode::Once),
+            },
+        )).id();
+    commands.entity(root_node).add_child(warning);
 }
 
 fn update_place(
-    player_place: Query<&race::placement_sensor::Place, With<player::Player>>,
+    player_place: Query<(&race::placement::Place, &player::Player)>,
     total_racers: Query<Entity, With<kart::Kart>>,
-    mut texts: Query<&mut Text, With<PlaceMarker>>,
+    mut texts: Query<(&mut Text, &PlaceMarker)>,
+) {
+    for (mut text, marker) in &mut texts {
+        for (place, player) in &player_place {
+            if player.0 == marker.0 {
+                text.sections[0].value = format!("Place: {} / {}", place.0, total_racers.iter().len());
+            }
+        }
+    }
+}
+
+fn flash_place(
+    mut commands: Commands,
+    mut place_changed_event_reader: EventReader<race::events::PlaceChanged>,
+    players: Query<&player::Player>,
+    mut texts: Query<(Entity, &mut Text, &PlaceMarker, Option<&mut PlaceFlash>)>,
+    time: Res<Time>,
+) {
+    for event in place_changed_event_reader.read() {
+        let Ok(player) = players.get(event.entity) else { continue };
+        let color = if event.place < event.previous { Color::GREEN } else { Color::RED };
+        for (entity, mut text, marker, _) in &mut texts {
+            if marker.0 == player.0 {
+                text.sections[0].style.color = color;
+                commands.entity(entity).insert(PlaceFlash(Timer::from_seconds(1.0, TimerMode::Once)));
+            }
+        }
+    }
+
+    for (entity, mut text, _, flash) in &mut texts {
+        if let Some(mut flash) = flash {
+            if flash.0.tick(time.delta()).just_finished() {
+                text.sections[0].style.color = Color::BLACK;
+                commands.entity(entity).remove::<PlaceFlash>();
+            }
+        }
+    }
+}
+
+fn update_warning(
+    mut lap_rejected_event_reader: EventReader<race::lap_check::LapRejected>,
+    players: Query<(&player::Player, &race::lap_check::WrongWay)>,
+    mut texts: Query<(&mut Text, &mut WarningMarker)>,
+    time: Res<Time>,
 ) {
-    for mut text in &mut texts {
-        for place in &player_place {
-            text.sections[0].value = format!("Place: {} / {}", place.0, total_racers.iter().len());
+    for event in lap_rejected_event_reader.read() {
+        let Ok((player, _)) = players.get(event.entity) else { continue };
+        for (_, mut marker) in &mut texts {
+            if marker.player == player.0 {
+                marker.lap_rejected = Timer::from_seconds(2.0, TimerMode::Once);
+            }
+        }
+    }
+
+    for (mut text, mut marker) in &mut texts {
+        marker.lap_rejected.tick(time.delta());
+        let is_wrong_way = players.iter().any(|(player, wrong_way)| player.0 == marker.player && wrong_way.is_active);
+        let warning = if !marker.lap_rejected.finished() {
+            "Lap not counted!"
+        } else if is_wrong_way {
+            "Wrong way!"
+        } else {
+            ""
+        };
+
+        if text.sections[0].value != warning {
+            text.sections[0].value = warning.to_string();
         }
     }
 }
 
 fn update_credits(
-    player_credits: Query<&points::Points, With<player::Player>>,
-    mut texts: Query<&mut Text, With<CreditsMarker>>,
+    player_credits: Query<(&points::Points, &player::Player, &tower::SelectedTower, &kart::KartClass)>,
+    mut texts: Query<(&mut Text, &CreditsMarker)>,
+    game_config: Res<config::GameConfig>,
+) {
+    for (mut text, marker) in &mut texts {
+        for (credit, player, selected_tower, class) in &player_credits {
+            if player.0 == marker.0 {
+                let cost = class.stats(&game_config).tower_cost(selected_tower.0.cost(&game_config));
+                text.sections[0].value = format!("{} credits\n{} ({})", credit.0, selected_tower.0.label(), cost);
+            }
+        }
+    }
+}
+
+fn update_held_item(
+    player_items: Query<(&items::HeldItem, &player::Player)>,
+    mut texts: Query<(&mut Text, &ItemMarker)>,
 ) {
-    for mut text in &mut texts {
-        for credit in &player_credits {
-            text.sections[0].value = format!("{} credits", credit.0);
+    for (mut text, marker) in &mut texts {
+        for (held_item, player) in &player_items {
+            if player.0 == marker.0 {
+                text.sections[0].value = format!("Item: {}", held_item.0.map(|power_up| power_up.label()).unwrap_or("-"));
+            }
         }
     }
 }
 
 fn update_lap_counter(
-    player_lap: Query<&race::LapCounter, With<player::Player>>,
-    mut texts: Query<&mut Text, With<LapMarker>>,
+    player_lap: Query<(&race::LapCounter, &player::Player)>,
+    mut texts: Query<(&mut Text, &LapMarker)>,
+    game_state: Res<game_settings::GameState>,
+) {
+    let laps = game_state.race_laps();
+    for (mut text, marker) in &mut texts {
+        for (lap, player) in &player_lap {
+            if player.0 == marker.0 {
+                text.sections[0].value = match laps {
+                    Some(laps) => format!("Lap {}/{}", lap.0.min(laps), laps),
+                    None => format!("Lap {}", lap.0),
+                };
+            }
+        }
+    }
+}
+
+fn update_lap_time(
+    player_lap_timers: Query<(&time_trial::LapTimer, &player::Player)>,
+    mut texts: Query<(&mut Text, &LapTimeMarker)>,
+    best_laps: Res<time_trial::BestLaps>,
+    game_state: Res<game_settings::GameState>,
 ) {
-    for mut text in &mut texts {
-        for lap in &player_lap {
-            text.sections[0].value = format!("Lap {}", lap.0);
+    let best_lap = best_laps.get(track::get(game_state.track));
+    for (mut text, marker) in &mut texts {
+        for (lap_timer, player) in &player_lap_timers {
+            if player.0 == marker.0 {
+                text.sections[0].value = format!(
+                    "Time {}\nSector {}\nBest {}",
+                    time_trial::format_time(game_state.game_time - lap_timer.lap_start),
+                    lap_timer.last_sector.map(time_trial::format_time).unwrap_or("-".to_string()),
+                    best_lap.map(|best| time_trial::format_time(best.lap.time)).unwrap_or("-".to_string()),
+                );
+            }
         }
     }
 }
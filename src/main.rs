@@ -11,11 +11,17 @@ mod menu;
 mod shaders;
 mod util;
 mod ui;
+mod headless;
 
 #[cfg(feature = "debug")]
 mod debug;
 
 fn main() {
+    if let Some(options) = headless::SimulationOptions::from_args() {
+        headless::run(options);
+        return;
+    }
+
     let mut app = App::new();
     app.insert_resource(AssetMetaCheck::Never);
 
@@ -89,6 +95,8 @@ pub enum AppState {
     TitleScreen,
     Splash,
     Settings,
+    TrackSelect,
+    Customize,
     InGame,
 }
 
@@ -97,13 +105,30 @@ pub enum IngameState {
     InGame,
     EndGame,
     PreGame, // haha yeaaah
+    Paused,
     #[default]
     Disabled,
 }
 
 use assets::command_ext::*;
-fn bootstrap(mut commands: Commands, mut clear_color: ResMut<ClearColor>) {
+fn bootstrap(mut commands: Commands, mut clear_color: ResMut<ClearColor>, mut game_state: ResMut<ingame::game_settings::GameState>) {
     clear_color.0 = Color::hex("FFFFFF").unwrap();
+
+    let saved_settings = util::settings::SavedSettings::load();
+    game_state.enable_shadows = saved_settings.enable_shadows;
+    game_state.enable_background = saved_settings.enable_background;
+    game_state.controller_type = saved_settings.controller_type;
+    game_state.player_name = saved_settings.player_name.clone();
+    game_state.player_color = saved_settings.player_color;
+    commands.insert_resource(saved_settings);
+
+    #[cfg(not(feature = "web"))]
+    if let Some(recording) = ingame::replay::from_args() {
+        commands.add(ingame::replay::WatchReplay { recording: Some(recording), seek_to: None });
+        commands.load_state(AppState::InGame);
+        return;
+    }
+
     #[cfg(feature = "debug")]
     {
         clear_color.0 = Color::hex("000000").unwrap();
@@ -3,11 +3,13 @@ use bevy::prelude::*;
 
 pub mod loader;
 mod setup;
+mod state;
 mod update;
 
 use self::{
     setup::setup,
-    update::handle_input
+    state::ControlRow,
+    update::{capture_binding, handle_input, highlight_selection, update_labels},
 };
 
 pub struct ControlsPlugin;
@@ -16,7 +18,10 @@ impl Plugin for ControlsPlugin {
         app.add_systems(OnEnter(AppState::Controls), setup)
             .init_resource::<ControlsState>()
             .add_systems(
-                Update, handle_input.run_if(in_state(AppState::Controls)),
+                Update,
+                (capture_binding, handle_input, highlight_selection, update_labels)
+                    .chain()
+                    .run_if(in_state(AppState::Controls)),
             )
             .add_systems(OnExit(AppState::Controls), cleanup::<CleanupMarker>);
     }
@@ -24,16 +29,25 @@ impl Plugin for ControlsPlugin {
 
 #[derive(Component)]
 struct CleanupMarker;
+#[derive(Component)]
+struct KeyLabelMarker;
+#[derive(Component)]
+struct ButtonLabelMarker;
+
 #[derive(Resource)]
 pub struct ControlsState {
-    pub cooldown: Timer
+    pub cooldown: Timer,
+    pub selected: ControlRow,
+    /// Waiting for the next key or button to bind to the selected row
+    pub is_listening: bool,
 }
 
 impl Default for ControlsState {
     fn default() -> Self {
         ControlsState {
-            cooldown: Timer::from_seconds(0.2, TimerMode::Once)
+            cooldown: Timer::from_seconds(0.2, TimerMode::Once),
+            selected: ControlRow::Go,
+            is_listening: false,
         }
     }
 }
-
@@ -1,17 +1,16 @@
-use super::{CleanupMarker, ControlsState};
+use super::{state::ControlRow, ButtonLabelMarker, CleanupMarker, ControlsState, KeyLabelMarker};
 use crate::ingame::game_settings;
 use crate::util::input::InputCommandsExt;
-use crate::assets;
+use crate::{assets, menu::MenuOption, ui};
 use bevy::prelude::*;
 
 pub fn setup(
     mut commands: Commands,
     game_assets: Res<assets::GameAssets>,
     game_state: Res<game_settings::GameState>,
-    mut meshes: ResMut<Assets<Mesh>>,
+    text_scaler: ui::text_size::TextScaler,
     mut controls_state: ResMut<ControlsState>,
 ) {
-    let camera_transform = Transform::from_xyz(0.0, 4., 0.0).looking_at(Vec3::ZERO, -Vec3::Z);
     commands.spawn((
         Camera3dBundle {
             camera: Camera { ..default() },
@@ -24,6 +23,21 @@ pub fn setup(
     commands.spawn_menu_input(CleanupMarker);
     *controls_state = ControlsState::default();
 
+    let text_style = |color: Color| TextStyle {
+        font: game_assets.font.clone(),
+        font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE * 0.6),
+        color,
+    };
+    let cell = |width: f32| NodeBundle {
+        style: Style {
+            width: Val::Percent(width),
+            display: Display::Flex,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        ..default()
+    };
+
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -32,7 +46,7 @@ pub fn setup(
                 position_type: PositionType::Relative,
                 justify_content: JustifyContent::Center,
                 align_items: AlignItems::Center,
-                flex_direction: FlexDirection::Column,
+                flex_direction: FlexDirection::Row,
                 ..default()
             },
             ..default()
@@ -41,7 +55,7 @@ pub fn setup(
         .with_children(|parent| {
             parent.spawn(ImageBundle {
                 style: Style {
-                    width: Val::Percent(100.0),
+                    width: Val::Percent(45.0),
                     height: Val::Auto,
                     ..default()
                 },
@@ -51,5 +65,95 @@ pub fn setup(
                 },
                 ..default()
             });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(50.0),
+                        height: Val::Percent(90.0),
+                        display: Display::Flex,
+                        flex_direction: FlexDirection::Column,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                width: Val::Percent(100.),
+                                height: Val::Percent(6.5),
+                                display: Display::Flex,
+                                flex_direction: FlexDirection::Row,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(cell(40.));
+                            for heading in ["Keyboard", "Gamepad"] {
+                                parent.spawn(cell(30.)).with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(heading, text_style(Color::BLACK)));
+                                });
+                            }
+                        });
+
+                    for row in ControlRow::get() {
+                        match row {
+                            ControlRow::Reset | ControlRow::Go => {
+                                parent
+                                    .spawn((
+                                        NodeBundle {
+                                            style: Style {
+                                                width: Val::Percent(30.),
+                                                height: Val::Percent(6.5),
+                                                display: Display::Flex,
+                                                margin: UiRect::top(Val::Percent(1.)),
+                                                align_items: AlignItems::Center,
+                                                align_self: AlignSelf::Center,
+                                                justify_content: JustifyContent::Center,
+                                                ..default()
+                                            },
+                                            ..default()
+                                        },
+                                        row,
+                                    ))
+                                    .with_children(|parent| {
+                                        parent.spawn((TextBundle::from_section(row.get_label(), text_style(Color::BLACK)), row));
+                                    });
+                            },
+                            _ => {
+                                parent
+                                    .spawn((
+                                        NodeBundle {
+                                            style: Style {
+                                                width: Val::Percent(100.),
+                                                height: Val::Percent(6.5),
+                                                display: Display::Flex,
+                                                flex_direction: FlexDirection::Row,
+                                                align_items: AlignItems::Center,
+                                                ..default()
+                                            },
+                                            ..default()
+                                        },
+                                        row,
+                                    ))
+                                    .with_children(|parent| {
+                                        parent.spawn(cell(40.)).with_children(|parent| {
+                                            parent.spawn((TextBundle::from_section(row.get_label(), text_style(Color::BLACK)), row));
+                                        });
+                                        parent.spawn(cell(30.)).with_children(|parent| {
+                                            parent.spawn((TextBundle::from_section("", text_style(Color::BLACK)), row, KeyLabelMarker));
+                                        });
+                                        parent.spawn(cell(30.)).with_children(|parent| {
+                                            parent.spawn((TextBundle::from_section("", text_style(Color::BLACK)), row, ButtonLabelMarker));
+                                        });
+                                    });
+                            },
+                        }
+                    }
+                });
         });
 }
@@ -0,0 +1,132 @@
+use crate::{menu::MenuOption, util::input::GameAction, util::settings};
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+/// A row on the controls screen, steering is split so each side of the axis can be bound
+#[derive(Component, Copy, Clone, PartialEq)]
+pub enum ControlRow {
+    Gas,
+    Brake,
+    SteerLeft,
+    SteerRight,
+    PlaceTower,
+    CycleTower,
+    PreviousTower,
+    UpgradeTower,
+    UseItem,
+    Pause,
+    Reset,
+    Go,
+}
+
+impl MenuOption<12> for ControlRow {
+    const ITEM: [ControlRow; 12] = [
+        ControlRow::Gas,
+        ControlRow::Brake,
+        ControlRow::SteerLeft,
+        ControlRow::SteerRight,
+        ControlRow::PlaceTower,
+        ControlRow::CycleTower,
+        ControlRow::PreviousTower,
+        ControlRow::UpgradeTower,
+        ControlRow::UseItem,
+        ControlRow::Pause,
+        ControlRow::Reset,
+        ControlRow::Go,
+    ];
+
+    fn get_label(&self) -> &str {
+        match self {
+            ControlRow::Gas => "Gas",
+            ControlRow::Brake => "Brake",
+            ControlRow::SteerLeft => "Steer Left",
+            ControlRow::SteerRight => "Steer Right",
+            ControlRow::PlaceTower => "Place Tower",
+            ControlRow::CycleTower => "Next Tower",
+            ControlRow::PreviousTower => "Previous Tower",
+            ControlRow::UpgradeTower => "Upgrade Tower",
+            ControlRow::UseItem => "Use Item",
+            ControlRow::Pause => "Pause",
+            ControlRow::Reset => "Reset",
+            ControlRow::Go => "Go!",
+        }
+    }
+}
+
+impl ControlRow {
+    pub fn action(&self) -> Option<GameAction> {
+        match self {
+            ControlRow::Gas => Some(GameAction::Gas),
+            ControlRow::Brake => Some(GameAction::Brake),
+            ControlRow::SteerLeft | ControlRow::SteerRight => Some(GameAction::Steer),
+            ControlRow::PlaceTower => Some(GameAction::PlaceTower),
+            ControlRow::CycleTower => Some(GameAction::CycleTower),
+            ControlRow::PreviousTower => Some(GameAction::PreviousTower),
+            ControlRow::UpgradeTower => Some(GameAction::UpgradeTower),
+            ControlRow::UseItem => Some(GameAction::UseItem),
+            ControlRow::Pause => Some(GameAction::Pause),
+            ControlRow::Reset | ControlRow::Go => None,
+        }
+    }
+
+    /// The first binding for this row in the given map
+    pub fn label(&self, input_map: &InputMap<GameAction>) -> String {
+        self.action()
+            .and_then(|action| {
+                input_map.get(action).into_iter().flatten().find_map(|input| match (self, input) {
+                    (ControlRow::SteerLeft, UserInput::VirtualAxis(axis)) => kind_label(&axis.negative),
+                    (ControlRow::SteerRight, UserInput::VirtualAxis(axis)) => kind_label(&axis.positive),
+                    (_, UserInput::Single(kind)) => kind_label(kind),
+                    _ => None,
+                })
+            })
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    /// Replaces the keys for this row with `key`
+    pub fn rebind_key(&self, bindings: &mut settings::ControlBindings, key: KeyCode) {
+        let Some(action) = self.action() else { return; };
+        let input: UserInput = match self {
+            ControlRow::SteerLeft | ControlRow::SteerRight => {
+                let current = bindings.keyboard
+                    .get(action)
+                    .into_iter()
+                    .flatten()
+                    .find_map(|input| match input {
+                        UserInput::VirtualAxis(axis) => Some(axis.clone()),
+                        _ => None,
+                    })
+                    .unwrap_or_else(VirtualAxis::ad);
+                if *self == ControlRow::SteerLeft {
+                    VirtualAxis { negative: key.into(), ..current }.into()
+                } else {
+                    VirtualAxis { positive: key.into(), ..current }.into()
+                }
+            },
+            _ => key.into(),
+        };
+
+        bindings.keyboard.clear_action(action);
+        bindings.keyboard.insert(input, action);
+    }
+
+    /// Replaces the buttons for this row with `button`, the stick always steers
+    pub fn rebind_button(&self, bindings: &mut settings::ControlBindings, button: GamepadButtonType) {
+        match self.action() {
+            Some(GameAction::Steer) | None => (),
+            Some(action) => {
+                bindings.gamepad.clear_action(action);
+                bindings.gamepad.insert(button, action);
+            },
+        }
+    }
+}
+
+fn kind_label(kind: &InputKind) -> Option<String> {
+    match kind {
+        InputKind::Keyboard(key) => Some(format!("{:?}", key)),
+        InputKind::GamepadButton(button) => Some(format!("{:?}", button)),
+        InputKind::SingleAxis(_) => Some("Stick".to_string()),
+        _ => None,
+    }
+}
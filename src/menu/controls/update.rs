@@ -1,26 +1,117 @@
 use crate::assets::command_ext::*;
-use crate::{assets, util::audio, util::input, AppState};
-use super::ControlsState;
+use crate::{assets, menu::MenuOption, ui, util::audio, util::input, util::settings, AppState};
+use super::{state::ControlRow, ButtonLabelMarker, ControlsState, KeyLabelMarker};
 use bevy::prelude::*;
 use leafwing_input_manager::prelude::*;
 
+/// Binds the next key or button pressed to the selected row. Steering stays on
+/// the stick so a button pressed on those rows just stops listening.
+pub fn capture_binding(
+    mut controls_state: ResMut<ControlsState>,
+    mut saved_settings: ResMut<settings::SavedSettings>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+) {
+    if !controls_state.is_listening {
+        return;
+    }
+
+    let row = controls_state.selected;
+    if let Some(key) = keys.get_just_pressed().next() {
+        row.rebind_key(&mut saved_settings.control_bindings, *key);
+    } else if let Some(button) = buttons.get_just_pressed().next() {
+        row.rebind_button(&mut saved_settings.control_bindings, button.button_type);
+    } else {
+        return;
+    }
+
+    saved_settings.save();
+    controls_state.is_listening = false;
+    // the key that was just bound might also select in the menu
+    controls_state.cooldown = ControlsState::default().cooldown;
+}
+
 pub fn handle_input(
     mut commands: Commands,
     action_state: Query<&ActionState<input::MenuAction>>,
     game_assets: Res<assets::GameAssets>,
+    saved_settings: Res<settings::SavedSettings>,
     mut audio: audio::GameAudio,
     mut controls_state: ResMut<ControlsState>,
     time: Res<Time>,
 ) {
     let action_state = action_state.single();
 
-    if controls_state.cooldown.tick(time.delta()).finished() {
-        if action_state.just_pressed(input::MenuAction::Select)
-            || action_state.just_pressed(input::MenuAction::Start)
-        {
-            audio.stop_bgm();
+    if !controls_state.cooldown.tick(time.delta()).finished() || controls_state.is_listening {
+        return;
+    }
+
+    if action_state.just_pressed(input::MenuAction::Up) {
+        audio.play_sfx(&game_assets.sfx_1);
+        controls_state.selected = controls_state.selected.previous();
+    }
+
+    if action_state.just_pressed(input::MenuAction::Down) {
+        audio.play_sfx(&game_assets.sfx_1);
+        controls_state.selected = controls_state.selected.next();
+    }
+
+    if action_state.just_pressed(input::MenuAction::Start)
+        || (action_state.just_pressed(input::MenuAction::Select) && controls_state.selected == ControlRow::Go)
+    {
+        audio.stop_bgm();
 
-            commands.load_state(AppState::InGame);
+        commands.load_state(AppState::InGame);
+    } else if action_state.just_pressed(input::MenuAction::Select) {
+        audio.play_sfx(&game_assets.sfx_1);
+        if controls_state.selected == ControlRow::Reset {
+            // audio reads the saved settings too so the updated copy replaces them
+            let saved_settings = settings::SavedSettings {
+                control_bindings: settings::ControlBindings::default(),
+                ..saved_settings.clone()
+            };
+            saved_settings.save();
+            commands.insert_resource(saved_settings);
+        } else {
+            controls_state.is_listening = true;
+        }
+    }
+}
+
+pub fn highlight_selection(
+    controls_state: Res<ControlsState>,
+    mut rows: Query<(&ControlRow, Option<&mut BackgroundColor>, Option<&mut Text>)>,
+) {
+    for (row, maybe_background_color, maybe_text) in &mut rows {
+        let (background_color, text_color) = if *row == controls_state.selected {
+            (ui::HOVERED_BUTTON, Color::WHITE)
+        } else {
+            (ui::NORMAL_BUTTON, Color::BLACK)
+        };
+        if let Some(mut background) = maybe_background_color {
+            *background = BackgroundColor(background_color);
+        }
+        if let Some(mut text) = maybe_text {
+            for text_section in text.sections.iter_mut() {
+                text_section.style.color = text_color;
+            }
         }
     }
 }
+
+pub fn update_labels(
+    controls_state: Res<ControlsState>,
+    saved_settings: Res<settings::SavedSettings>,
+    mut key_labels: Query<(&ControlRow, &mut Text), (With<KeyLabelMarker>, Without<ButtonLabelMarker>)>,
+    mut button_labels: Query<(&ControlRow, &mut Text), (With<ButtonLabelMarker>, Without<KeyLabelMarker>)>,
+) {
+    let bindings = &saved_settings.control_bindings;
+    let is_waiting = |row: &ControlRow| controls_state.is_listening && *row == controls_state.selected;
+
+    for (row, mut text) in &mut key_labels {
+        text.sections[0].value = if is_waiting(row) { "Press...".to_string() } else { row.label(&bindings.keyboard) };
+    }
+    for (row, mut text) in &mut button_labels {
+        text.sections[0].value = if is_waiting(row) { "Press...".to_string() } else { row.label(&bindings.gamepad) };
+    }
+}
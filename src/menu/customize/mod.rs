@@ -0,0 +1,35 @@
+use crate::{cleanup, AppState};
+use bevy::prelude::*;
+
+pub mod loader;
+mod setup;
+mod state;
+mod update;
+
+use self::{
+    setup::setup,
+    state::CustomizeState,
+    update::{edit_name, handle_input, highlight_selection, update_values},
+};
+
+pub struct CustomizePlugin;
+impl Plugin for CustomizePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Customize), setup)
+            .init_resource::<CustomizeState>()
+            .add_systems(
+                Update,
+                (edit_name, handle_input, highlight_selection, update_values)
+                    .chain()
+                    .run_if(in_state(AppState::Customize)),
+            )
+            .add_systems(OnExit(AppState::Customize), cleanup::<CleanupMarker>);
+    }
+}
+
+#[derive(Component)]
+struct CleanupMarker;
+#[derive(Component)]
+pub struct ValueDisplayMarker;
+#[derive(Component)]
+pub struct ColorSwatchMarker;
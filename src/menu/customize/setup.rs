@@ -0,0 +1,178 @@
+use super::state::{CustomizeOption, CustomizeState};
+use super::{CleanupMarker, ColorSwatchMarker, ValueDisplayMarker};
+use crate::ingame::game_settings;
+use crate::util::input::InputCommandsExt;
+use crate::{assets, menu::MenuOption, ui};
+use bevy::prelude::*;
+
+pub fn setup(
+    mut commands: Commands,
+    game_assets: Res<assets::GameAssets>,
+    game_state: Res<game_settings::GameState>,
+    text_scaler: ui::text_size::TextScaler,
+    mut customize_state: ResMut<CustomizeState>,
+) {
+    *customize_state = CustomizeState {
+        screen_cooldown: Timer::from_seconds(0.1, TimerMode::Once),
+        selected_option: CustomizeOption::Go,
+        name: game_state.player_name.clone(),
+        color: game_state.player_color,
+        is_editing: false,
+    };
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera { ..default() },
+            ..default()
+        },
+        CleanupMarker,
+        ViewVisibility::default(),
+        Visibility::Visible,
+    ));
+    commands.spawn_menu_input(CleanupMarker);
+
+    let text_style = TextStyle {
+        font: game_assets.font.clone(),
+        font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE),
+        color: Color::WHITE,
+    };
+
+    let root_node = commands
+        .spawn((
+            NodeBundle {
+                z_index: ZIndex::Global(-100),
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::FlexStart,
+                    ..default()
+                },
+                transform: Transform::from_xyz(0., 0., -1.),
+                ..default()
+            },
+            CleanupMarker,
+        ))
+        .id();
+
+    let title_text = commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(20.),
+                display: Display::Flex,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|builder| {
+            builder.spawn(TextBundle {
+                text: Text::from_section(
+                    "Customize Kart",
+                    TextStyle {
+                        font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE * 1.2),
+                        color: Color::BLACK,
+                        ..text_style.clone()
+                    },
+                ),
+                ..default()
+            });
+        })
+        .id();
+
+    let options = CustomizeOption::get()
+        .into_iter()
+        .map(|option| match option {
+            CustomizeOption::Go => commands
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Percent(20.),
+                            height: Val::Percent(15.),
+                            display: Display::Flex,
+                            padding: UiRect::all(Val::Percent(2.)),
+                            margin: UiRect {
+                                top: Val::Percent(10.),
+                                ..default()
+                            },
+                            align_items: AlignItems::Center,
+                            align_self: AlignSelf::Center,
+                            justify_content: JustifyContent::Center,
+                            border: UiRect::all(Val::Percent(1.0)),
+                            flex_direction: FlexDirection::Row,
+                            ..default()
+                        },
+                        border_color: BorderColor(Color::WHITE),
+                        ..default()
+                    },
+                    option,
+                ))
+                .with_children(|builder| {
+                    builder.spawn((TextBundle::from_section(option.get_label(), text_style.clone()), option));
+                })
+                .id(),
+            _ => commands
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Percent(100.),
+                            height: Val::Percent(15.),
+                            display: Display::Flex,
+                            padding: UiRect::all(Val::Percent(2.)),
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::SpaceBetween,
+                            flex_direction: FlexDirection::Row,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    option,
+                ))
+                .with_children(|builder| {
+                    builder.spawn((TextBundle::from_section(format!("{}:", option.get_label()), text_style.clone()), option));
+
+                    builder
+                        .spawn(NodeBundle {
+                            style: Style {
+                                height: Val::Percent(100.),
+                                width: Val::Percent(40.),
+                                display: Display::Flex,
+                                align_items: AlignItems::Center,
+                                flex_direction: FlexDirection::Row,
+                                justify_content: if option == CustomizeOption::Color { JustifyContent::SpaceBetween } else { JustifyContent::Center },
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|builder| {
+                            if option == CustomizeOption::Color {
+                                builder.spawn((TextBundle::from_section("<", text_style.clone()), option));
+                                builder.spawn((
+                                    NodeBundle {
+                                        style: Style {
+                                            width: Val::Px(text_scaler.scale(ui::DEFAULT_FONT_SIZE)),
+                                            height: Val::Px(text_scaler.scale(ui::DEFAULT_FONT_SIZE)),
+                                            ..default()
+                                        },
+                                        background_color: BackgroundColor(customize_state.color()),
+                                        ..default()
+                                    },
+                                    ColorSwatchMarker,
+                                ));
+                                builder.spawn((TextBundle::from_section(">", text_style.clone()), option));
+                            } else {
+                                builder.spawn((TextBundle::from_section("", text_style.clone()), option, ValueDisplayMarker));
+                            }
+                        });
+                })
+                .id(),
+        })
+        .collect::<Vec<_>>();
+
+    commands.entity(root_node).add_child(title_text);
+
+    for entity in options {
+        commands.entity(root_node).add_child(entity);
+    }
+}
@@ -0,0 +1,94 @@
+use crate::util::num_ext::*;
+use crate::{ingame::game_settings, menu::MenuOption};
+use bevy::prelude::*;
+
+/// Longest name that still fits above a kart
+pub const MAX_NAME_LENGTH: usize = 12;
+
+/// What the d-pad can spell a name with when there's no keyboard
+const PICKER_CHARACTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 ";
+
+#[derive(Default, Resource)]
+pub struct CustomizeState {
+    pub screen_cooldown: Timer,
+    pub selected_option: CustomizeOption,
+    pub name: String,
+    /// Index into the default `kart_colors`
+    pub color: usize,
+    /// Typed characters and the gamepad d-pad go into the name instead of moving around the menu
+    pub is_editing: bool,
+}
+
+impl CustomizeState {
+    pub fn display(&self, option: &CustomizeOption) -> String {
+        match option {
+            CustomizeOption::Name if self.is_editing => format!("{}_", self.name),
+            CustomizeOption::Name => self.name.clone(),
+            option => option.get_label().to_string(),
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        game_settings::GameState::default().kart_colors[self.color]
+    }
+
+    /// Adds a character for the gamepad picker to change
+    pub fn push_character(&mut self) {
+        if self.name.chars().count() < MAX_NAME_LENGTH {
+            self.name.push(PICKER_CHARACTERS.chars().next().unwrap());
+        }
+    }
+
+    /// Steps the last character of the name through `PICKER_CHARACTERS`,
+    /// anything typed that isn't in there starts over from the first one
+    pub fn cycle_character(&mut self, forward: bool) {
+        let characters = PICKER_CHARACTERS.chars().collect::<Vec<_>>();
+        let Some(last) = self.name.pop() else {
+            self.name.push(characters[0]);
+            return;
+        };
+
+        let next = match characters.iter().position(|c| *c == last.to_ascii_uppercase()) {
+            Some(index) if forward => index.circular_increment(0, characters.len() - 1),
+            Some(index) => index.circular_decrement(0, characters.len() - 1),
+            None => 0,
+        };
+        self.name.push(characters[next]);
+    }
+
+    pub fn increment(&mut self) {
+        if self.selected_option == CustomizeOption::Color {
+            self.color = self.color.circular_increment(0, game_settings::GameState::default().kart_colors.len() - 1);
+        }
+    }
+
+    pub fn decrement(&mut self) {
+        if self.selected_option == CustomizeOption::Color {
+            self.color = self.color.circular_decrement(0, game_settings::GameState::default().kart_colors.len() - 1);
+        }
+    }
+}
+
+#[derive(Component, Copy, Clone, PartialEq, Default)]
+pub enum CustomizeOption {
+    #[default]
+    Name,
+    Color,
+    Go,
+}
+
+impl MenuOption<3> for CustomizeOption {
+    const ITEM: [CustomizeOption; 3] = [
+        CustomizeOption::Name,
+        CustomizeOption::Color,
+        CustomizeOption::Go,
+    ];
+
+    fn get_label(&self) -> &str {
+        match self {
+            CustomizeOption::Name => "Name",
+            CustomizeOption::Color => "Color",
+            CustomizeOption::Go => "Go!",
+        }
+    }
+}
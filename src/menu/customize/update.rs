@@ -0,0 +1,151 @@
+use super::{
+    state::{CustomizeOption, CustomizeState, MAX_NAME_LENGTH},
+    ColorSwatchMarker, ValueDisplayMarker,
+};
+use crate::assets::command_ext::*;
+use crate::{assets, ingame::game_settings, menu, ui, util::audio, util::input, util::settings, AppState};
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+use menu::MenuOption;
+
+pub fn highlight_selection(
+    customize_state: Res<CustomizeState>,
+    mut options: Query<(&CustomizeOption, Option<&mut BackgroundColor>, Option<&mut Text>)>,
+) {
+    for (&option, maybe_background_color, maybe_text) in &mut options {
+        let (background_color, text_color) = if option == customize_state.selected_option {
+            (ui::HOVERED_BUTTON, Color::WHITE)
+        } else {
+            (ui::NORMAL_BUTTON, Color::BLACK)
+        };
+        if let Some(mut background) = maybe_background_color {
+            *background = BackgroundColor(background_color);
+        }
+        if let Some(mut text) = maybe_text {
+            for text_section in text.sections.iter_mut() {
+                text_section.style.color = text_color;
+            }
+        }
+    }
+}
+
+pub fn update_values(
+    customize_state: Res<CustomizeState>,
+    mut values: Query<(&mut Text, &CustomizeOption), With<ValueDisplayMarker>>,
+    mut swatches: Query<&mut BackgroundColor, With<ColorSwatchMarker>>,
+) {
+    for (mut text, option) in &mut values {
+        text.sections[0].value = customize_state.display(option);
+    }
+    for mut swatch in &mut swatches {
+        *swatch = BackgroundColor(customize_state.color());
+    }
+}
+
+/// Types into the name while it's being edited, enter or escape stops editing.
+/// Gamepads spell it with the d-pad: up and down change the last character,
+/// right adds one, left removes one and south or start stops editing.
+pub fn edit_name(
+    mut customize_state: ResMut<CustomizeState>,
+    mut received_characters: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+) {
+    if !customize_state.is_editing {
+        received_characters.clear();
+        return;
+    }
+
+    for event in received_characters.read() {
+        if !event.char.is_control() && customize_state.name.chars().count() < MAX_NAME_LENGTH {
+            customize_state.name.push(event.char);
+        }
+    }
+
+    if keys.just_pressed(KeyCode::Back) {
+        customize_state.name.pop();
+    }
+
+    let mut is_done = keys.any_just_pressed([KeyCode::Return, KeyCode::Escape]);
+    for gamepad in gamepads.iter() {
+        let just_pressed = |button_type| buttons.just_pressed(GamepadButton { gamepad, button_type });
+        if just_pressed(GamepadButtonType::DPadUp) {
+            customize_state.cycle_character(true);
+        }
+        if just_pressed(GamepadButtonType::DPadDown) {
+            customize_state.cycle_character(false);
+        }
+        if just_pressed(GamepadButtonType::DPadRight) {
+            customize_state.push_character();
+        }
+        if just_pressed(GamepadButtonType::DPadLeft) {
+            customize_state.name.pop();
+        }
+        is_done |= just_pressed(GamepadButtonType::South) || just_pressed(GamepadButtonType::Start);
+    }
+
+    if is_done {
+        customize_state.is_editing = false;
+        customize_state.name = customize_state.name.trim().to_string();
+        // enter also selects in the menu
+        customize_state.screen_cooldown = Timer::from_seconds(0.1, TimerMode::Once);
+    }
+}
+
+pub fn handle_input(
+    mut commands: Commands,
+    mut customize_state: ResMut<CustomizeState>,
+    action_state: Query<&ActionState<input::MenuAction>>,
+    game_assets: Res<assets::GameAssets>,
+    mut game_state: ResMut<game_settings::GameState>,
+    mut saved_settings: ResMut<settings::SavedSettings>,
+    mut audio: audio::GameAudio,
+    time: Res<Time>,
+) {
+    if !customize_state.screen_cooldown.tick(time.delta()).finished() || customize_state.is_editing {
+        return;
+    }
+
+    let action_state = action_state.single();
+
+    if action_state.just_pressed(input::MenuAction::Up) {
+        audio.play_sfx(&game_assets.sfx_1);
+        customize_state.selected_option = customize_state.selected_option.previous();
+    }
+
+    if action_state.just_pressed(input::MenuAction::Down) {
+        audio.play_sfx(&game_assets.sfx_1);
+        customize_state.selected_option = customize_state.selected_option.next();
+    }
+
+    if action_state.just_pressed(input::MenuAction::Left) {
+        audio.play_sfx(&game_assets.sfx_1);
+        customize_state.decrement();
+    }
+
+    if action_state.just_pressed(input::MenuAction::Right) {
+        audio.play_sfx(&game_assets.sfx_1);
+        customize_state.increment();
+    }
+
+    if action_state.just_pressed(input::MenuAction::Select) && customize_state.selected_option == CustomizeOption::Name {
+        audio.play_sfx(&game_assets.sfx_1);
+        customize_state.is_editing = true;
+    } else if (action_state.just_pressed(input::MenuAction::Select)
+        || action_state.just_pressed(input::MenuAction::Start))
+        && customize_state.selected_option == CustomizeOption::Go
+    {
+        audio.play_sfx(&game_assets.sfx_1);
+        if !customize_state.name.is_empty() {
+            game_state.player_name = customize_state.name.clone();
+        }
+        game_state.player_color = customize_state.color;
+
+        saved_settings.player_name = game_state.player_name.clone();
+        saved_settings.player_color = game_state.player_color;
+        saved_settings.save();
+
+        commands.load_state(AppState::Instructions);
+    }
+}
@@ -5,6 +5,8 @@ pub mod controls;
 pub mod instructions;
 pub mod title_screen;
 pub mod settings;
+pub mod track_select;
+pub mod customize;
 
 pub struct MenuPlugin;
 
@@ -16,11 +18,13 @@ impl Plugin for MenuPlugin {
             controls::ControlsPlugin,
             instructions::InstructionsPlugin,
             settings::SettingsMenuPlugin,
+            track_select::TrackSelectPlugin,
+            customize::CustomizePlugin,
         ));
     }
 }
 
-trait MenuOption<const N: usize>
+pub(crate) trait MenuOption<const N: usize>
 where
     Self: PartialEq + Sized + Clone + Copy,
 {
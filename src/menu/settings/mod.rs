@@ -8,9 +8,9 @@ mod update;
 
 use self::{
     setup::setup,
-    state::SettingsMenuState,
     update::{handle_input, highlight_selection, update_values},
 };
+pub use state::{Settings, SettingsMenuState, MAX_LAPS};
 
 pub struct SettingsMenuPlugin;
 impl Plugin for SettingsMenuPlugin {
@@ -1,7 +1,7 @@
 use super::state::{Settings, SettingsMenuState};
 use super::{CleanupMarker, SettingDisplayMarker};
 use crate::util::input::InputCommandsExt;
-use crate::{assets, menu, menu::MenuOption, ui};
+use crate::{assets, menu, menu::MenuOption, ui, util::settings};
 use bevy::prelude::*;
 
 pub fn setup(
@@ -9,21 +9,13 @@ pub fn setup(
     game_assets: Res<assets::GameAssets>,
     text_scaler: ui::text_size::TextScaler,
     mut setting_state: ResMut<SettingsMenuState>,
+    saved_settings: Res<settings::SavedSettings>,
 ) {
     *setting_state = SettingsMenuState::default();
 
-    #[cfg(feature = "web")]
-    {
-        setting_state.enable_shadows = 1;
-        setting_state.enable_background = 1;
-    }
-
-    #[cfg(not(feature = "web"))]
-    {
-        setting_state.enable_shadows = 1;
-        setting_state.enable_background = 1;
-    }
+    setting_state.load(&saved_settings);
 
+    setting_state.number_of_players = 1;
     setting_state.selected_setting = Settings::Go;
     setting_state.screen_cooldown = Timer::from_seconds(0.1, TimerMode::Once);
     commands.spawn((
@@ -60,7 +52,7 @@ pub fn setup(
         .spawn(NodeBundle {
             style: Style {
                 width: Val::Percent(100.),
-                height: Val::Percent(20.),
+                height: Val::Percent(15.),
                 display: Display::Flex,
                 justify_content: JustifyContent::Center,
                 ..default()
@@ -90,11 +82,11 @@ pub fn setup(
                     NodeBundle {
                         style: Style {
                             width: Val::Percent(20.),
-                            height: Val::Percent(15.),
+                            height: Val::Percent(10.),
                             display: Display::Flex,
-                            padding: UiRect::all(Val::Percent(2.)),
+                            padding: UiRect::all(Val::Percent(1.)),
                             margin: UiRect {
-                                top: Val::Percent(10.),
+                                top: Val::Percent(2.),
                                 ..default()
                             },
                             align_items: AlignItems::Center,
@@ -131,9 +123,9 @@ pub fn setup(
                     NodeBundle {
                         style: Style {
                             width: Val::Percent(100.),
-                            height: Val::Percent(15.),
+                            height: Val::Percent(6.5),
                             display: Display::Flex,
-                            padding: UiRect::all(Val::Percent(2.)),
+                            padding: UiRect::horizontal(Val::Percent(2.)),
                             align_items: AlignItems::Center,
                             justify_content: JustifyContent::SpaceBetween,
                             flex_direction: FlexDirection::Row,
@@ -150,7 +142,7 @@ pub fn setup(
                                 format!("{}:", setting.get_label()),
                                 TextStyle {
                                     font: game_assets.font.clone(),
-                                    font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE),
+                                    font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE * 0.7),
                                     color: Color::WHITE,
                                 },
                             ),
@@ -179,7 +171,7 @@ pub fn setup(
                                         "<",
                                         TextStyle {
                                             font: game_assets.font.clone(),
-                                            font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE),
+                                            font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE * 0.7),
                                             color: Color::WHITE,
                                         },
                                     ),
@@ -193,7 +185,7 @@ pub fn setup(
                                         "5",
                                         TextStyle {
                                             font: game_assets.font.clone(),
-                                            font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE),
+                                            font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE * 0.7),
                                             color: Color::WHITE,
                                         },
                                     ),
@@ -208,7 +200,7 @@ pub fn setup(
                                         ">",
                                         TextStyle {
                                             font: game_assets.font.clone(),
-                                            font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE),
+                                            font_size: text_scaler.scale(ui::DEFAULT_FONT_SIZE * 0.7),
                                             color: Color::WHITE,
                                         },
                                     ),
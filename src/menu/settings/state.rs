@@ -1,16 +1,61 @@
 use crate::util::num_ext::*;
-use crate::{menu::MenuOption};
+use crate::{menu::MenuOption, ingame::{bot, config}, util::settings};
 use bevy::prelude::*;
 
+pub const MAX_LAPS: usize = 9;
+/// Volumes are picked in tenths
+const VOLUME_STEPS: usize = 10;
+
 #[derive(Default, Resource)]
 pub struct SettingsMenuState {
     pub screen_cooldown: Timer,
     pub selected_setting: Settings,
     pub enable_shadows: isize,
     pub enable_background: isize,
+    pub number_of_players: usize,
+    pub difficulty: bot::Difficulty,
+    /// 0 keeps the track's own lap target
+    pub laps: usize,
+    pub enable_music: isize,
+    pub master_volume: usize,
+    pub music_volume: usize,
+    pub sfx_volume: usize,
+    pub engine_volume: usize,
+}
+
+fn to_steps(volume: f32) -> usize {
+    (volume * VOLUME_STEPS as f32).round().clamp(0., VOLUME_STEPS as f32) as usize
+}
+
+fn from_steps(steps: usize) -> f32 {
+    steps as f32 / VOLUME_STEPS as f32
 }
 
 impl SettingsMenuState {
+    pub fn load(&mut self, saved_settings: &settings::SavedSettings) {
+        self.enable_shadows = saved_settings.enable_shadows as isize;
+        self.enable_background = saved_settings.enable_background as isize;
+        self.enable_music = saved_settings.enable_music as isize;
+        self.master_volume = to_steps(saved_settings.master_volume);
+        self.music_volume = to_steps(saved_settings.music_volume);
+        self.sfx_volume = to_steps(saved_settings.sfx_volume);
+        self.engine_volume = to_steps(saved_settings.engine_volume);
+    }
+
+    /// The saved settings with this menu's choices applied
+    pub fn apply_to(&self, saved_settings: &settings::SavedSettings) -> settings::SavedSettings {
+        settings::SavedSettings {
+            enable_shadows: self.enable_shadows == 1,
+            enable_background: self.enable_background == 1,
+            enable_music: self.enable_music == 1,
+            master_volume: from_steps(self.master_volume),
+            music_volume: from_steps(self.music_volume),
+            sfx_volume: from_steps(self.sfx_volume),
+            engine_volume: from_steps(self.engine_volume),
+            ..saved_settings.clone()
+        }
+    }
+
     pub fn display(&self, setting: &Settings) -> String {
         match setting {
             Settings::EnableBackground => match self.enable_background {
@@ -21,6 +66,20 @@ impl SettingsMenuState {
                 1 => "     On     ".to_string(),
                 _ => "     Off    ".to_string(),
             },
+            Settings::Players => format!("     {}      ", self.number_of_players),
+            Settings::Difficulty => format!("   {}   ", self.difficulty.label()),
+            Settings::Laps => match self.laps {
+                0 => "   Track    ".to_string(),
+                laps => format!("     {}      ", laps),
+            },
+            Settings::EnableMusic => match self.enable_music {
+                1 => "     On     ".to_string(),
+                _ => "     Off    ".to_string(),
+            },
+            Settings::MasterVolume => format!("    {:>3}%    ", self.master_volume * 100 / VOLUME_STEPS),
+            Settings::MusicVolume => format!("    {:>3}%    ", self.music_volume * 100 / VOLUME_STEPS),
+            Settings::SfxVolume => format!("    {:>3}%    ", self.sfx_volume * 100 / VOLUME_STEPS),
+            Settings::EngineVolume => format!("    {:>3}%    ", self.engine_volume * 100 / VOLUME_STEPS),
             setting => setting.get_label().to_string(),
         }
     }
@@ -33,6 +92,22 @@ impl SettingsMenuState {
             Settings::EnableBackground  => {
                 self.enable_background = self.enable_background.circular_increment(0, 1);
             },
+            Settings::Players => {
+                self.number_of_players = self.number_of_players.circular_increment(1, config::MAX_NUMBER_OF_PLAYERS);
+            },
+            Settings::Difficulty => {
+                self.difficulty = self.difficulty.next();
+            },
+            Settings::Laps => {
+                self.laps = self.laps.circular_increment(0, MAX_LAPS);
+            },
+            Settings::EnableMusic => {
+                self.enable_music = self.enable_music.circular_increment(0, 1);
+            },
+            Settings::MasterVolume => self.master_volume = (self.master_volume + 1).min(VOLUME_STEPS),
+            Settings::MusicVolume => self.music_volume = (self.music_volume + 1).min(VOLUME_STEPS),
+            Settings::SfxVolume => self.sfx_volume = (self.sfx_volume + 1).min(VOLUME_STEPS),
+            Settings::EngineVolume => self.engine_volume = (self.engine_volume + 1).min(VOLUME_STEPS),
             _ => (),
         }
     }
@@ -45,6 +120,22 @@ impl SettingsMenuState {
             Settings::EnableBackground  => {
                 self.enable_background = self.enable_background.circular_decrement(0, 1);
             },
+            Settings::Players => {
+                self.number_of_players = self.number_of_players.circular_decrement(1, config::MAX_NUMBER_OF_PLAYERS);
+            },
+            Settings::Difficulty => {
+                self.difficulty = self.difficulty.previous();
+            },
+            Settings::Laps => {
+                self.laps = self.laps.circular_decrement(0, MAX_LAPS);
+            },
+            Settings::EnableMusic => {
+                self.enable_music = self.enable_music.circular_decrement(0, 1);
+            },
+            Settings::MasterVolume => self.master_volume = self.master_volume.saturating_sub(1),
+            Settings::MusicVolume => self.music_volume = self.music_volume.saturating_sub(1),
+            Settings::SfxVolume => self.sfx_volume = self.sfx_volume.saturating_sub(1),
+            Settings::EngineVolume => self.engine_volume = self.engine_volume.saturating_sub(1),
             _ => (),
         }
     }
@@ -55,13 +146,29 @@ pub enum Settings {
     #[default]
     EnableShadows,
     EnableBackground,
+    Players,
+    Difficulty,
+    Laps,
+    MasterVolume,
+    EnableMusic,
+    MusicVolume,
+    SfxVolume,
+    EngineVolume,
     Go,
 }
 
-impl MenuOption<3> for Settings {
-    const ITEM: [Settings; 3] = [
+impl MenuOption<11> for Settings {
+    const ITEM: [Settings; 11] = [
         Settings::EnableShadows,
         Settings::EnableBackground,
+        Settings::Players,
+        Settings::Difficulty,
+        Settings::Laps,
+        Settings::MasterVolume,
+        Settings::EnableMusic,
+        Settings::MusicVolume,
+        Settings::SfxVolume,
+        Settings::EngineVolume,
         Settings::Go,
     ];
 
@@ -69,6 +176,14 @@ impl MenuOption<3> for Settings {
         match self {
             Settings::EnableShadows => "Shadows",
             Settings::EnableBackground => "Background",
+            Settings::Players => "Players",
+            Settings::Difficulty => "Bots",
+            Settings::Laps => "Laps",
+            Settings::MasterVolume => "Volume",
+            Settings::EnableMusic => "Music",
+            Settings::MusicVolume => "Music Volume",
+            Settings::SfxVolume => "Sounds",
+            Settings::EngineVolume => "Engines",
             Settings::Go => "Go!",
         }
     }
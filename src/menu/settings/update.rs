@@ -3,7 +3,7 @@ use super::{
     SettingDisplayMarker,
 };
 use crate::assets::command_ext::*;
-use crate::{assets, util::audio, ingame::game_settings, util::input, menu, ui, AppState, };
+use crate::{assets, util::audio, ingame::game_settings, util::input, util::settings, menu, ui, AppState, };
 use bevy::prelude::*;
 use leafwing_input_manager::prelude::*;
 use menu::MenuOption;
@@ -50,6 +50,7 @@ pub fn handle_input(
     action_state: Query<&ActionState<input::MenuAction>>,
     game_assets: Res<assets::GameAssets>,
     mut game_state: ResMut<game_settings::GameState>,
+    saved_settings: Res<settings::SavedSettings>,
     mut audio: audio::GameAudio,
     gamepads: Res<Gamepads>,
     buttons: Res<Input<GamepadButton>>,
@@ -80,11 +81,13 @@ pub fn handle_input(
         if axis_pair.x() == 1.0 {
             audio.play_sfx(&game_assets.sfx_1);
             setting_state.increment();
+            commands.insert_resource(setting_state.apply_to(&saved_settings));
             *axis_timer = Timer::from_seconds(0.2, TimerMode::Once);
         }
         if axis_pair.x() == -1.0 {
             audio.play_sfx(&game_assets.sfx_1);
             setting_state.decrement();
+            commands.insert_resource(setting_state.apply_to(&saved_settings));
             *axis_timer = Timer::from_seconds(0.2, TimerMode::Once);
         }
     }
@@ -102,11 +105,14 @@ pub fn handle_input(
     if action_state.just_pressed(input::MenuAction::Left) {
         audio.play_sfx(&game_assets.sfx_1);
         setting_state.decrement();
+        // volumes are heard straight away, everything else only matters once the game starts
+        commands.insert_resource(setting_state.apply_to(&saved_settings));
     }
 
     if action_state.just_pressed(input::MenuAction::Right) {
         audio.play_sfx(&game_assets.sfx_1);
         setting_state.increment();
+        commands.insert_resource(setting_state.apply_to(&saved_settings));
     }
 
     if (action_state.just_pressed(input::MenuAction::Select)
@@ -125,12 +131,27 @@ pub fn handle_input(
         }
 
         const MIN_DIFFICULTY: f32 = 0.5;
-        *game_state = game_settings::GameState::initialize(
-            setting_state.enable_shadows == 1,
-            setting_state.enable_background == 1,
-            controller_type
-        );
+        *game_state = game_settings::GameState {
+            bot_difficulty: setting_state.difficulty,
+            laps: (setting_state.laps > 0).then_some(setting_state.laps),
+            player_name: saved_settings.player_name.clone(),
+            player_color: saved_settings.player_color,
+            ..game_settings::GameState::initialize(
+                setting_state.enable_shadows == 1,
+                setting_state.enable_background == 1,
+                controller_type,
+                setting_state.number_of_players,
+            )
+        };
 
-        commands.load_state(AppState::Instructions);
+        // audio reads the saved settings too so the updated copy replaces them
+        let saved_settings = settings::SavedSettings {
+            controller_type: game_state.controller_type,
+            ..setting_state.apply_to(&saved_settings)
+        };
+        saved_settings.save();
+        commands.insert_resource(saved_settings);
+
+        commands.load_state(AppState::TrackSelect);
     }
 }
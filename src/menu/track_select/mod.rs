@@ -0,0 +1,32 @@
+use crate::{cleanup, AppState};
+use bevy::prelude::*;
+
+pub mod loader;
+mod setup;
+mod state;
+mod update;
+
+use self::{
+    setup::setup,
+    state::TrackSelectState,
+    update::{handle_input, highlight_selection, update_values},
+};
+
+pub struct TrackSelectPlugin;
+impl Plugin for TrackSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::TrackSelect), setup)
+            .init_resource::<TrackSelectState>()
+            .add_systems(
+                Update,
+                (highlight_selection, handle_input, update_values, )
+                    .run_if(in_state(AppState::TrackSelect)),
+            )
+            .add_systems(OnExit(AppState::TrackSelect), cleanup::<CleanupMarker>);
+    }
+}
+
+#[derive(Component)]
+struct CleanupMarker;
+#[derive(Component)]
+pub struct TrackDisplayMarker;
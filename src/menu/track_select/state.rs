@@ -0,0 +1,100 @@
+use crate::util::num_ext::*;
+use crate::{menu::MenuOption, menu::settings::MAX_LAPS, ingame::game_settings, ingame::kart, ingame::track};
+use bevy::prelude::*;
+
+#[derive(Default, Resource)]
+pub struct TrackSelectState {
+    pub screen_cooldown: Timer,
+    pub selected_option: TrackSelectOption,
+    pub track: usize,
+    pub time_trial: bool,
+    pub kart_class: kart::KartClass,
+}
+
+impl TrackSelectState {
+    pub fn display(&self, option: &TrackSelectOption, game_state: &game_settings::GameState) -> String {
+        let track = track::get(self.track);
+        match option {
+            TrackSelectOption::Track => track.name.to_string(),
+            TrackSelectOption::Mode => if self.time_trial { "Time Trial" } else { "Race" }.to_string(),
+            TrackSelectOption::Laps => match game_state.race_laps() {
+                Some(laps) => format!("{}", laps),
+                None => "Last Kart Standing".to_string(),
+            },
+            TrackSelectOption::Kart => self.kart_class.label().to_string(),
+            option => option.get_label().to_string(),
+        }
+    }
+
+    pub fn increment(&mut self, game_state: &mut game_settings::GameState) {
+        match self.selected_option {
+            TrackSelectOption::Track => self.track = self.track.circular_increment(0, track::TRACKS.len() - 1),
+            TrackSelectOption::Mode => self.time_trial = !self.time_trial,
+            TrackSelectOption::Kart => self.kart_class = kart::KartClass::ALL[self.kart_class_index().circular_increment(0, kart::KartClass::ALL.len() - 1)],
+            // time trials always run the track's own lap count
+            TrackSelectOption::Laps if self.time_trial => (),
+            TrackSelectOption::Laps => game_state.laps = lap_option(game_state.laps.unwrap_or(0).circular_increment(0, MAX_LAPS)),
+            _ => (),
+        }
+        self.apply(game_state);
+    }
+
+    pub fn decrement(&mut self, game_state: &mut game_settings::GameState) {
+        match self.selected_option {
+            TrackSelectOption::Track => self.track = self.track.circular_decrement(0, track::TRACKS.len() - 1),
+            TrackSelectOption::Mode => self.time_trial = !self.time_trial,
+            TrackSelectOption::Kart => self.kart_class = kart::KartClass::ALL[self.kart_class_index().circular_decrement(0, kart::KartClass::ALL.len() - 1)],
+            // time trials always run the track's own lap count
+            TrackSelectOption::Laps if self.time_trial => (),
+            TrackSelectOption::Laps => game_state.laps = lap_option(game_state.laps.unwrap_or(0).circular_decrement(0, MAX_LAPS)),
+            _ => (),
+        }
+        self.apply(game_state);
+    }
+
+    /// Writes the selection into the game state, laps are set on it directly
+    pub fn apply(&self, game_state: &mut game_settings::GameState) {
+        game_state.track = self.track;
+        game_state.time_trial = self.time_trial;
+        game_state.kart_class = self.kart_class;
+    }
+
+    fn kart_class_index(&self) -> usize {
+        kart::KartClass::ALL.iter().position(|class| *class == self.kart_class).unwrap_or_default()
+    }
+}
+
+/// 0 leaves the lap count to the track
+fn lap_option(laps: usize) -> Option<usize> {
+    if laps == 0 { None } else { Some(laps) }
+}
+
+#[derive(Component, Copy, Clone, PartialEq, Default)]
+pub enum TrackSelectOption {
+    #[default]
+    Track,
+    Mode,
+    Laps,
+    Kart,
+    Go,
+}
+
+impl MenuOption<5> for TrackSelectOption {
+    const ITEM: [TrackSelectOption; 5] = [
+        TrackSelectOption::Track,
+        TrackSelectOption::Mode,
+        TrackSelectOption::Laps,
+        TrackSelectOption::Kart,
+        TrackSelectOption::Go,
+    ];
+
+    fn get_label(&self) -> &str {
+        match self {
+            TrackSelectOption::Track => "Track",
+            TrackSelectOption::Mode => "Mode",
+            TrackSelectOption::Laps => "Laps",
+            TrackSelectOption::Kart => "Kart",
+            TrackSelectOption::Go => "Go!",
+        }
+    }
+}
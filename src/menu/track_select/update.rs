@@ -0,0 +1,122 @@
+use super::{
+    state::{TrackSelectOption, TrackSelectState},
+    TrackDisplayMarker,
+};
+use crate::assets::command_ext::*;
+use crate::{assets, util::audio, ingame::game_settings, util::input, menu, ui, AppState, };
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+use menu::MenuOption;
+
+pub fn highlight_selection(
+    track_select_state: Res<TrackSelectState>,
+    mut options: Query<(&TrackSelectOption, Option<&mut BackgroundColor>, Option<&mut Text>)>,
+) {
+    for (&option, maybe_background_color, maybe_text) in &mut options {
+        if option == track_select_state.selected_option {
+            if let Some(mut background_color) = maybe_background_color {
+                *background_color = BackgroundColor(ui::HOVERED_BUTTON);
+            }
+            if let Some(mut text) = maybe_text {
+                for text_section in text.sections.iter_mut() {
+                    text_section.style.color = Color::WHITE;
+                }
+            }
+        } else {
+            if let Some(mut background_color) = maybe_background_color {
+                *background_color = BackgroundColor(ui::NORMAL_BUTTON);
+            }
+            if let Some(mut text) = maybe_text {
+                for text_section in text.sections.iter_mut() {
+                    text_section.style.color = Color::BLACK;
+                }
+            }
+        }
+    }
+}
+
+pub fn update_values(
+    track_select_state: Res<TrackSelectState>,
+    game_state: Res<game_settings::GameState>,
+    mut options: Query<(&mut Text, &TrackSelectOption), With<TrackDisplayMarker>>,
+) {
+    for (mut text, option) in &mut options {
+        text.sections[0].value = track_select_state.display(option, &game_state);
+    }
+}
+
+pub fn handle_input(
+    mut commands: Commands,
+    mut track_select_state: ResMut<TrackSelectState>,
+    action_state: Query<&ActionState<input::MenuAction>>,
+    game_assets: Res<assets::GameAssets>,
+    mut game_state: ResMut<game_settings::GameState>,
+    mut audio: audio::GameAudio,
+    mut axis_timer: Local<Timer>,
+    time: Res<Time>,
+) {
+    if !track_select_state.screen_cooldown.tick(time.delta()).finished() {
+        return;
+    }
+
+    let action_state = action_state.single();
+
+    if axis_timer.tick(time.delta()).finished() && action_state.pressed(input::MenuAction::Move) {
+        let axis_pair = action_state
+            .clamped_axis_pair(input::MenuAction::Move)
+            .unwrap();
+        if axis_pair.y() == 1.0 {
+            audio.play_sfx(&game_assets.sfx_1);
+            track_select_state.selected_option = track_select_state.selected_option.previous();
+            *axis_timer = Timer::from_seconds(0.2, TimerMode::Once);
+        }
+        if axis_pair.y() == -1.0 {
+            audio.play_sfx(&game_assets.sfx_1);
+            track_select_state.selected_option = track_select_state.selected_option.next();
+            *axis_timer = Timer::from_seconds(0.2, TimerMode::Once);
+        }
+
+        if axis_pair.x() == 1.0 {
+            audio.play_sfx(&game_assets.sfx_1);
+            track_select_state.increment(&mut game_state);
+            *axis_timer = Timer::from_seconds(0.2, TimerMode::Once);
+        }
+        if axis_pair.x() == -1.0 {
+            audio.play_sfx(&game_assets.sfx_1);
+            track_select_state.decrement(&mut game_state);
+            *axis_timer = Timer::from_seconds(0.2, TimerMode::Once);
+        }
+    }
+
+    if action_state.just_pressed(input::MenuAction::Up) {
+        audio.play_sfx(&game_assets.sfx_1);
+        track_select_state.selected_option = track_select_state.selected_option.previous();
+    }
+
+    if action_state.just_pressed(input::MenuAction::Down) {
+        audio.play_sfx(&game_assets.sfx_1);
+        track_select_state.selected_option = track_select_state.selected_option.next();
+    }
+
+    if action_state.just_pressed(input::MenuAction::Left) {
+        audio.play_sfx(&game_assets.sfx_1);
+        track_select_state.decrement(&mut game_state);
+    }
+
+    if action_state.just_pressed(input::MenuAction::Right) {
+        audio.play_sfx(&game_assets.sfx_1);
+        track_select_state.increment(&mut game_state);
+    }
+
+    if (action_state.just_pressed(input::MenuAction::Select)
+        || action_state.just_pressed(input::MenuAction::Start))
+        && track_select_state.selected_option == TrackSelectOption::Go
+    {
+        audio.play_sfx(&game_assets.sfx_1);
+        track_select_state.apply(&mut game_state);
+        if game_state.time_trial {
+            game_state.number_of_players = 1;
+        }
+        commands.load_state(AppState::Customize);
+    }
+}
@@ -82,12 +82,13 @@ impl<T: Component> Command for SpawnFollowText<T> {
 
 use bevy::text::TextLayoutInfo;
 fn update_text_position(
-    mut text_query: Query<(&mut Style, &TextLayoutInfo, &mut FollowText)>,
+    mut commands: Commands,
+    mut text_query: Query<(Entity, &mut Style, &TextLayoutInfo, &mut FollowText)>,
     mesh_query: Query<&Transform>,
     camera_query: Query<(&Camera, &GlobalTransform), With<Camera>>,
     time: Res<Time>,
 ) {
-    for (mut style, text_layout_info, mut follow_text) in text_query.iter_mut() {
+    for (entity, mut style, text_layout_info, mut follow_text) in text_query.iter_mut() {
         if let Ok(mesh_position) = mesh_query.get(follow_text.following) {
             let translation = Vec3::new(
                 mesh_position.translation.x,
@@ -108,6 +109,8 @@ fn update_text_position(
                     }
                 }
             }
+        } else {
+            commands.entity(entity).despawn_recursive();
         }
     }
 }
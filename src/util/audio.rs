@@ -1,14 +1,43 @@
 use bevy::{ecs::system::SystemParam, prelude::*};
-use bevy_kira_audio::{AudioApp, AudioChannel, AudioControl, AudioPlugin, AudioSource};
+use bevy_kira_audio::{AudioApp, AudioChannel, AudioControl, AudioPlugin, AudioSource, OggLoader, WavLoader};
 use bevy_kira_audio::prelude::*;
 use std::marker::PhantomData;
+use super::settings;
 
 pub struct GameAudioPlugin;
 impl Plugin for GameAudioPlugin {
     fn build(&self, app: &mut App) {
         app.add_audio_channel::<MusicChannel>()
             .add_audio_channel::<SoundChannel>()
-            .add_plugins(AudioPlugin);
+            .add_plugins(AudioPlugin)
+            .add_systems(Update, apply_music_volume.run_if(resource_changed::<settings::SavedSettings>()));
+    }
+}
+
+/// The assets and channels the game plays to, without kira's audio output.
+/// For the headless simulation where there's nothing to play sounds on.
+pub struct SilentAudioPlugin;
+impl Plugin for SilentAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<AudioSource>()
+            .init_asset::<AudioInstance>()
+            .init_asset_loader::<OggLoader>()
+            .init_asset_loader::<WavLoader>()
+            .init_resource::<Audio>()
+            .init_resource::<AudioChannel<MusicChannel>>()
+            .init_resource::<AudioChannel<SoundChannel>>()
+            .init_resource::<DynamicAudioChannels>();
+    }
+}
+
+fn apply_music_volume(
+    music_channel: Res<AudioChannel<MusicChannel>>,
+    settings: Res<settings::SavedSettings>,
+) {
+    if settings.is_music_enabled() {
+        music_channel.set_volume(settings.mixed_music_volume());
+    } else {
+        music_channel.stop();
     }
 }
 
@@ -22,6 +51,7 @@ pub struct GameAudio<'w, 's> {
     music_channel: Res<'w, AudioChannel<MusicChannel>>,
     sound_channel: Res<'w, AudioChannel<SoundChannel>>,
     dynamic_channel: ResMut<'w, DynamicAudioChannels>,
+    settings: Res<'w, settings::SavedSettings>,
 
     #[system_param(ignore)]
     phantom: PhantomData<&'s ()>,
@@ -30,9 +60,8 @@ pub struct GameAudio<'w, 's> {
 impl<'w, 's> GameAudio<'w, 's> {
     pub fn play_bgm(&mut self, handle: &Handle<AudioSource>) {
         self.music_channel.stop();
-        #[cfg(not(feature = "no_music"))]
-        {
-            self.music_channel.set_volume(0.5);
+        if self.settings.is_music_enabled() {
+            self.music_channel.set_volume(self.settings.mixed_music_volume());
             self.music_channel.play(handle.clone()).looped();
         }
     }
@@ -42,28 +71,25 @@ impl<'w, 's> GameAudio<'w, 's> {
     }
 
     pub fn play_sfx(&mut self, handle: &Handle<AudioSource>) {
-        self.sound_channel.set_volume(0.5);
+        self.sound_channel.set_volume(self.settings.mixed_sfx_volume());
         self.sound_channel.play(handle.clone());
     }
 
     pub fn play_sfx_with_volume(&mut self, handle: &Handle<AudioSource>, channel: &str, volume: f32, looped: bool) {
         let channel =
             if !self.dynamic_channel.is_channel(channel) {
-                println!("CREATING");
-                 self.dynamic_channel.create_channel(channel)
+                self.dynamic_channel.create_channel(channel)
             } else {
                 self.dynamic_channel.channel(channel)
             };
 
         if looped {
             if !channel.is_playing_sound() {
-                println!("er");
                 channel.play(handle.clone()).looped();
             }
         } else {
-            println!("uh");
             channel.play(handle.clone());
         }
-        channel.set_volume(volume as f64);
+        channel.set_volume(volume as f64 * self.settings.mixed_sfx_volume());
     }
 }
@@ -1,5 +1,5 @@
 use bevy::prelude::*;
-use crate::{AppState, ingame::player, ingame::config, util::audio};
+use crate::{AppState, ingame::kart, ingame::player, ingame::config, util::audio, util::settings};
 use bevy_kira_audio::AudioSource;
 use bevy_kira_audio::prelude::*;
 
@@ -7,7 +7,7 @@ pub struct AudioEmitterPlugin;
 
 impl Plugin for AudioEmitterPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(SpacialAudio { max_distance: config::AUDIO_DISTANCE });
+        app.add_systems(PostUpdate, mix_spatial_audio);
         //app.add_systems(Update, emit_audios.run_if(in_state(AppState::InGame)));
     }
 }
@@ -20,11 +20,39 @@ pub struct AudioEmitter {
     pub audio: Handle<AudioSource>,
 }
 
+/// Fades sounds played by kira's emitters with their distance to the receiver
+/// and scales them by the engine or sound volume. Replaces kira's own spatial
+/// audio which sets instance volumes directly and would skip the mixer.
+fn mix_spatial_audio(
+    receivers: Query<&GlobalTransform, With<AudioReceiver>>,
+    emitters: Query<(&GlobalTransform, &bevy_kira_audio::prelude::AudioEmitter, Has<kart::Kart>)>,
+    mut audio_instances: ResMut<Assets<AudioInstance>>,
+    game_config: Res<config::GameConfig>,
+    settings: Res<settings::SavedSettings>,
+) {
+    let Ok(receiver) = receivers.get_single() else { return };
+
+    for (emitter_transform, emitter, is_engine) in &emitters {
+        let sound_path = emitter_transform.translation() - receiver.translation();
+        let distance_volume = (1. - sound_path.length() / game_config.audio_distance).clamp(0., 1.).powi(2) as f64;
+        let panning = (receiver.right().angle_between(sound_path).cos() + 1.) / 2.;
+        let mixer_volume = if is_engine { settings.mixed_engine_volume() } else { settings.mixed_sfx_volume() };
+
+        for instance in emitter.instances.iter() {
+            if let Some(instance) = audio_instances.get_mut(instance) {
+                instance.set_volume(distance_volume * mixer_volume, AudioTween::default());
+                instance.set_panning(if panning.is_nan() { 0.5 } else { panning as f64 }, AudioTween::default());
+            }
+        }
+    }
+}
+
 fn emit_audios( 
     mut commands: Commands,
     mut audio: audio::GameAudio,
     mut emitters: Query<(Entity, &mut AudioEmitter, &Transform)>,
     players: Query<&Transform, With<player::Player>>,
+    game_config: Res<config::GameConfig>,
     time: Res<Time>
 ) {
     for (entity, mut emitter, emitter_transform) in &mut emitters {
@@ -33,8 +61,8 @@ fn emit_audios(
         } else {
             for player in &players {
                 let distance = player.translation.distance(emitter_transform.translation);
-                if distance < config::AUDIO_DISTANCE {
-                    let volume = 1. - (distance / config::AUDIO_DISTANCE);
+                if distance < game_config.audio_distance {
+                    let volume = 1. - (distance / game_config.audio_distance);
                     audio.play_sfx_with_volume(&emitter.audio, &emitter.id, volume, emitter.looped);
                 }
             }
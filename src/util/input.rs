@@ -1,12 +1,15 @@
 use bevy::ecs::bundle::Bundle;
 use bevy::prelude::*;
 use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::util::settings;
 
 pub struct InputPlugin;
 
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(InputManagerPlugin::<MenuAction>::default());
+        app.add_plugins(InputManagerPlugin::<MenuAction>::default())
+            .add_plugins(InputManagerPlugin::<GameAction>::default());
     }
 }
 
@@ -22,6 +25,23 @@ pub enum MenuAction {
     Back,
 }
 
+/// What a player can do while racing. Steer is an axis, negative is left
+#[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug, Reflect, Serialize, Deserialize)]
+pub enum GameAction {
+    Gas,
+    Brake,
+    Steer,
+    PlaceTower,
+    CycleTower,
+    PreviousTower,
+    UpgradeTower,
+    UseItem,
+    Pause,
+}
+
+/// How hard a steering key turns the kart, the stick is scaled so full lock is still 1
+pub const KEYBOARD_STEER: f32 = 0.8;
+
 pub(crate) trait InputCommandsExt {
     fn spawn_menu_input(&mut self, cleanup_marker: impl Component);
 }
@@ -55,6 +75,31 @@ pub fn create_menu_input_for_player(player: usize) -> impl Bundle {
     }
 }
 
+/// Connected gamepads are handed out to players in the order they were connected
+pub fn gamepad_for_player(gamepads: &Gamepads, player: usize) -> Option<Gamepad> {
+    let mut connected = gamepads.iter().collect::<Vec<_>>();
+    connected.sort_by_key(|gamepad| gamepad.id);
+    connected.get(player).copied()
+}
+
+/// Every player drives with their own gamepad, the first player can also use the keyboard.
+/// A lone player can pick up whichever gamepad is connected.
+pub fn create_game_input_for_player(player: usize, bindings: &settings::ControlBindings, gamepad: Option<Gamepad>, is_only_player: bool) -> impl Bundle {
+    // without a gamepad of their own a player would read everyone else's
+    let mut input_map = if is_only_player || gamepad.is_some() { bindings.gamepad.clone() } else { InputMap::default() };
+    if player == 0 {
+        input_map.merge(&bindings.keyboard);
+    }
+    // merging clears the gamepad so this has to come after
+    if let (false, Some(gamepad)) = (is_only_player, gamepad) {
+        input_map.set_gamepad(gamepad);
+    }
+    InputManagerBundle::<GameAction> {
+        action_state: ActionState::default(),
+        input_map,
+    }
+}
+
 const KEYBOARD_INPUTS: [(KeyCode, MenuAction); 12] = [
     (KeyCode::Space, MenuAction::Select),
     (KeyCode::Return, MenuAction::Select),
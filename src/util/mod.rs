@@ -5,11 +5,12 @@ pub mod screen_shake;
 pub mod audio;
 pub mod audio_emitter;
 pub mod input;
+pub mod settings;
 
 pub struct UtilPlugin;
 impl Plugin for UtilPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((scene_hook::HookPlugin, audio::GameAudioPlugin, input::InputPlugin, audio_emitter::AudioEmitterPlugin));
+        app.add_plugins((scene_hook::HookPlugin, audio::GameAudioPlugin, input::InputPlugin, audio_emitter::AudioEmitterPlugin, settings::SettingsPlugin));
     }
 }
 
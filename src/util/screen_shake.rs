@@ -1,16 +1,27 @@
 use bevy::prelude::*;
 use bevy::ecs::system::{Command, SystemState};
 use bevy_camera_shake::Shake3d;
+use crate::ingame::camera::PlayerCamera;
 
 const DEFAULT_TRAUMA: f32 = 0.25;
 pub struct CameraShake {
-    trauma: f32
+    trauma: f32,
+    player: Option<usize>,
 }
 
 impl CameraShake {
     pub fn new(trauma: f32) -> Self {
         CameraShake {
-            trauma
+            trauma,
+            player: None,
+        }
+    }
+
+    /// Only shakes the camera following the given player
+    pub fn for_player(player: usize) -> Self {
+        CameraShake {
+            player: Some(player),
+            ..default()
         }
     }
 }
@@ -23,10 +34,18 @@ impl Default for CameraShake {
 
 impl Command for CameraShake {
     fn apply(self, world: &mut World) {
-        let mut system_state: SystemState<Query<&mut Shake3d>> = SystemState::new(world);
+        let mut system_state: SystemState<(Query<(&mut Shake3d, &Children)>, Query<&PlayerCamera>)> = SystemState::new(world);
+        let (mut shakeables, player_cameras) = system_state.get_mut(world);
+
+        for (mut shakeable, children) in &mut shakeables {
+            let is_target = match self.player {
+                Some(player) => children.iter().any(|c| player_cameras.get(*c).is_ok_and(|p| p.0 == player)),
+                None => true,
+            };
 
-        for mut shakeable in &mut system_state.get_mut(world) {
-            shakeable.trauma = f32::min(shakeable.trauma + self.trauma, 1.0);
+            if is_target {
+                shakeable.trauma = f32::min(shakeable.trauma + self.trauma, 1.0);
+            }
         }
     }
 }
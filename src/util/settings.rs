@@ -0,0 +1,184 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::{ingame::game_settings, util::input::{GameAction, KEYBOARD_STEER}};
+
+#[cfg(not(feature = "web"))]
+const SAVE_DIRECTORY: &str = "saves";
+#[cfg(not(feature = "web"))]
+const SETTINGS_PATH: &str = "saves/settings.ron";
+#[cfg(feature = "web")]
+const STORAGE_KEY: &str = "go_tower_go_settings";
+
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SavedSettings>();
+    }
+}
+
+/// Gameplay bindings for each kind of device. Every player's gamepad uses the
+/// same map and the keyboard one is added for the first player.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlBindings {
+    pub keyboard: InputMap<GameAction>,
+    pub gamepad: InputMap<GameAction>,
+}
+
+impl Default for ControlBindings {
+    fn default() -> Self {
+        let mut keyboard = InputMap::new([
+            (KeyCode::W, GameAction::Gas),
+            (KeyCode::Z, GameAction::Gas),
+            (KeyCode::Up, GameAction::Gas),
+            (KeyCode::S, GameAction::Brake),
+            (KeyCode::Down, GameAction::Brake),
+            (KeyCode::Space, GameAction::PlaceTower),
+            (KeyCode::E, GameAction::CycleTower),
+            (KeyCode::Tab, GameAction::CycleTower),
+            (KeyCode::F, GameAction::UpgradeTower),
+            (KeyCode::R, GameAction::UseItem),
+            (KeyCode::Escape, GameAction::Pause),
+            (KeyCode::P, GameAction::Pause),
+        ]);
+        keyboard.insert(VirtualAxis::ad(), GameAction::Steer)
+                .insert(VirtualAxis::horizontal_arrow_keys(), GameAction::Steer);
+
+        let mut gamepad = InputMap::new([
+            (GamepadButtonType::South, GameAction::Gas),
+            (GamepadButtonType::East, GameAction::Brake),
+            (GamepadButtonType::RightTrigger, GameAction::Brake),
+            (GamepadButtonType::West, GameAction::PlaceTower),
+            (GamepadButtonType::North, GameAction::UseItem),
+            (GamepadButtonType::LeftTrigger, GameAction::PlaceTower),
+            (GamepadButtonType::DPadRight, GameAction::CycleTower),
+            (GamepadButtonType::RightTrigger2, GameAction::CycleTower),
+            (GamepadButtonType::DPadLeft, GameAction::PreviousTower),
+            (GamepadButtonType::LeftTrigger2, GameAction::PreviousTower),
+            (GamepadButtonType::DPadUp, GameAction::UpgradeTower),
+            (GamepadButtonType::Start, GameAction::Pause),
+        ]);
+        // scaled up so the stick still reaches full lock, see KEYBOARD_STEER
+        gamepad.insert(
+            SingleAxis {
+                sensitivity: 1. / KEYBOARD_STEER,
+                ..SingleAxis::symmetric(GamepadAxisType::LeftStickX, 0.)
+            },
+            GameAction::Steer,
+        );
+
+        ControlBindings { keyboard, gamepad }
+    }
+}
+
+/// Settings kept between runs, saved to `saves/settings.ron` or the browser's
+/// local storage on web. Loaded once in `bootstrap`.
+#[derive(Clone, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedSettings {
+    pub enable_shadows: bool,
+    pub enable_background: bool,
+    pub enable_music: bool,
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    /// Kart engines, which play constantly so get their own slider
+    pub engine_volume: f32,
+    pub controller_type: game_settings::ControllerType,
+    pub control_bindings: ControlBindings,
+    pub player_name: String,
+    /// Index into the default `kart_colors`
+    pub player_color: usize,
+}
+
+impl Default for SavedSettings {
+    fn default() -> Self {
+        SavedSettings {
+            enable_shadows: true,
+            enable_background: true,
+            enable_music: true,
+            master_volume: 1.0,
+            music_volume: 0.5,
+            sfx_volume: 0.5,
+            engine_volume: 0.5,
+            controller_type: game_settings::ControllerType::Keyboard,
+            control_bindings: ControlBindings::default(),
+            player_name: game_settings::DEFAULT_PLAYER_NAME.to_string(),
+            player_color: game_settings::DEFAULT_PLAYER_COLOR,
+        }
+    }
+}
+
+impl SavedSettings {
+    /// Music can also be turned off for good with the `no_music` feature
+    pub fn is_music_enabled(&self) -> bool {
+        self.enable_music && !cfg!(feature = "no_music")
+    }
+
+    pub fn mixed_music_volume(&self) -> f64 {
+        (self.master_volume * self.music_volume) as f64
+    }
+
+    pub fn mixed_sfx_volume(&self) -> f64 {
+        (self.master_volume * self.sfx_volume) as f64
+    }
+
+    pub fn mixed_engine_volume(&self) -> f64 {
+        (self.master_volume * self.engine_volume) as f64
+    }
+
+    pub fn load() -> Self {
+        let mut settings: SavedSettings = Self::read()
+            .and_then(|contents| ron::from_str(&contents).map_err(|e| warn!("couldn't read settings: {}", e)).ok())
+            .unwrap_or_default();
+
+        // the file can be edited by hand, don't let it index past the kart colors
+        if settings.player_color >= game_settings::GameState::default().kart_colors.len() {
+            warn!("saved player color {} doesn't exist, using the default", settings.player_color);
+            settings.player_color = game_settings::DEFAULT_PLAYER_COLOR;
+        }
+
+        settings
+    }
+
+    #[cfg(not(feature = "web"))]
+    fn read() -> Option<String> {
+        std::fs::read_to_string(SETTINGS_PATH).ok()
+    }
+
+    #[cfg(feature = "web")]
+    fn read() -> Option<String> {
+        web_sys::window()?
+            .local_storage()
+            .ok()??
+            .get_item(STORAGE_KEY)
+            .ok()?
+    }
+
+    pub fn save(&self) {
+        let result = ron::to_string(self)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| Self::write(&contents));
+
+        if let Err(error) = result {
+            warn!("couldn't save settings: {}", error);
+        }
+    }
+
+    #[cfg(not(feature = "web"))]
+    fn write(contents: &str) -> Result<(), String> {
+        std::fs::create_dir_all(SAVE_DIRECTORY)
+            .and_then(|_| std::fs::write(SETTINGS_PATH, contents))
+            .map_err(|e| e.to_string())
+    }
+
+    #[cfg(feature = "web")]
+    fn write(contents: &str) -> Result<(), String> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or("no local storage".to_string())?
+            .set_item(STORAGE_KEY, contents)
+            .map_err(|e| format!("{:?}", e))
+    }
+}